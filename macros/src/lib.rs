@@ -0,0 +1,224 @@
+//! Procedural macros of kung, re-exported by the main crate.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, BinOp, Block, Error, Expr, ExprIf, ExprLit, ExprMatch, Ident, Lit, LitInt, Pat, Result, Stmt, Token};
+
+struct Input {
+    scope: Ident,
+    stmts: Vec<Stmt>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let scope = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let stmts = Block::parse_within(input)?;
+        Ok(Input { scope, stmts })
+    }
+}
+
+/// Describes the logic of a scope with Rust syntax: `hdl!(s => ...)` where
+/// `s` is the `&mut Scope` the statements are added to.
+///
+/// * `a = expr;` assigns to a signal and `mem[addr] = expr;` writes a memory,
+///   integer literals like `a = 0;` are as wide as the destination,
+/// * `if`/`else if`/`else` become `when`/`elsewhen`/`otherwise`,
+/// * `match` becomes a `case`, `a | b` arms match several values, `_` is the
+///   default and string patterns like `"1??0"` make it a `casez`,
+/// * comparisons and `&&`/`||` build the matching expressions.
+///
+/// Other statements are kept as they are.
+#[proc_macro]
+pub fn hdl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as Input);
+    match stmts(&input.scope, &input.stmts) {
+        Ok(tokens) => quote!({ #tokens }).into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn stmts(scope: &Ident, stmts: &[Stmt]) -> Result<TokenStream2> {
+    let mut tokens = TokenStream2::new();
+    for stmt in stmts {
+        tokens.extend(match stmt {
+            Stmt::Expr(expr) | Stmt::Semi(expr, _) => statement(scope, expr)?,
+            other => quote!(#other),
+        });
+    }
+    Ok(tokens)
+}
+
+// body of a branch, a block or a single statement
+fn body(scope: &Ident, expr: &Expr) -> Result<TokenStream2> {
+    match expr {
+        Expr::Block(block) => stmts(scope, &block.block.stmts),
+        other => statement(scope, other),
+    }
+}
+
+fn statement(scope: &Ident, expr: &Expr) -> Result<TokenStream2> {
+    match expr {
+        Expr::Assign(assign) => {
+            match &*assign.left {
+                Expr::Path(dest) => {
+                    let right = assigned(&assign.right, quote!(::kung::hdl::Operand::width(&#dest)));
+                    Ok(quote_spanned!(assign.span()=> #scope.assign(&#dest, #right);))
+                },
+                Expr::Index(index) => {
+                    let mem = &index.expr;
+                    let addr = op(&index.index);
+                    let right = assigned(&assign.right, quote!(#mem.element_width()));
+                    Ok(quote_spanned!(assign.span()=> #scope.write(&#mem, #addr, #right);))
+                },
+                other => Err(Error::new_spanned(other, "only signals and memory words can be assigned")),
+            }
+        },
+        Expr::If(expr) => branches(scope, expr),
+        Expr::Match(expr) => case(scope, expr),
+        Expr::Block(block) => stmts(scope, &block.block.stmts),
+        other => Ok(quote!(#other;)),
+    }
+}
+
+fn condition(expr: &Expr) -> Result<TokenStream2> {
+    if let Expr::Let(expr) = expr {
+        return Err(Error::new_spanned(expr, "`if let` can't be used in hardware conditions"));
+    }
+    Ok(op(expr))
+}
+
+fn branches(scope: &Ident, expr: &ExprIf) -> Result<TokenStream2> {
+    let cond = condition(&expr.cond)?;
+    let then = stmts(scope, &expr.then_branch.stmts)?;
+    let mut tokens = quote_spanned!(expr.if_token.span=> #scope.when(#cond, |#scope| { #then }));
+
+    let mut rest = &expr.else_branch;
+    while let Some((_, branch)) = rest {
+        match &**branch {
+            Expr::If(elif) => {
+                let cond = condition(&elif.cond)?;
+                let then = stmts(scope, &elif.then_branch.stmts)?;
+                tokens.extend(quote_spanned!(elif.if_token.span=> .elsewhen(#cond, |#scope| { #then })));
+                rest = &elif.else_branch;
+            },
+            other => {
+                let otherwise = body(scope, other)?;
+                tokens.extend(quote!(.otherwise(|#scope| { #otherwise })));
+                rest = &None;
+            },
+        }
+    }
+    Ok(quote!(#tokens;))
+}
+
+fn case(scope: &Ident, expr: &ExprMatch) -> Result<TokenStream2> {
+    let on = op(&expr.expr);
+    let mut tokens = quote!(#scope.switch(#on));
+
+    let wildcards = expr.arms.iter().any(|arm| match &arm.pat {
+        Pat::Lit(lit) => matches!(&*lit.expr, Expr::Lit(lit) if matches!(lit.lit, Lit::Str(_))),
+        _ => false,
+    });
+    if wildcards {
+        tokens.extend(quote!(.casez()));
+    }
+
+    for (i, arm) in expr.arms.iter().enumerate() {
+        if let Some((_, guard)) = &arm.guard {
+            return Err(Error::new_spanned(guard, "match guards can't be used in hardware case statements"));
+        }
+        let arm_body = body(scope, &arm.body)?;
+        let pat = &arm.pat;
+
+        tokens.extend(match pat {
+            Pat::Wild(_) => {
+                if i + 1 != expr.arms.len() {
+                    return Err(Error::new_spanned(pat, "the default arm has to be the last one"));
+                }
+                quote!(.default(|#scope| { #arm_body }))
+            },
+            Pat::Lit(lit) if matches!(&*lit.expr, Expr::Lit(l) if matches!(l.lit, Lit::Str(_))) => {
+                quote_spanned!(pat.span()=> .pattern(#lit, |#scope| { #arm_body }))
+            },
+            Pat::Or(or) => {
+                let values = or.cases.iter().map(label).collect::<Result<Vec<_>>>()?;
+                quote_spanned!(pat.span()=> .is_any(vec![#(#values),*], |#scope| { #arm_body }))
+            },
+            other => {
+                let value = label(other)?;
+                quote_spanned!(pat.span()=> .is(#value, |#scope| { #arm_body }))
+            },
+        });
+    }
+    Ok(quote!(#tokens;))
+}
+
+// value compared by a case arm
+fn label(pat: &Pat) -> Result<TokenStream2> {
+    match pat {
+        Pat::Lit(lit) => Ok(quote!(#lit)),
+        Pat::Path(path) => Ok(quote!(#path)),
+        Pat::Ident(ident) if ident.subpat.is_none() && ident.by_ref.is_none() && ident.mutability.is_none() => {
+            let ident = &ident.ident;
+            Ok(quote!(#ident))
+        },
+        other => Err(Error::new_spanned(other, "case arms can only match values, `_` or wildcard strings")),
+    }
+}
+
+// expression as an `Op`, errors about its type point at it
+fn op(expr: &Expr) -> TokenStream2 {
+    let value = value(expr);
+    quote_spanned!(expr.span()=> ::kung::hdl::expr::op(#value))
+}
+
+// right side of an assignment, integer literals become constants of the
+// width of the destination
+fn assigned(expr: &Expr, width: TokenStream2) -> TokenStream2 {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) => {
+            let value = LitInt::new(&format!("{}u64", lit.base10_digits()), lit.span());
+            quote_spanned!(lit.span()=> ::kung::hdl::expr::op(::kung::hdl::Const::new(#value, #width)))
+        },
+        other => op(other),
+    }
+}
+
+// expressions with the comparisons and logical operators turned into the
+// methods building them
+fn value(expr: &Expr) -> TokenStream2 {
+    match expr {
+        Expr::Binary(bin) => {
+            let left = value(&bin.left);
+            let right = value(&bin.right);
+            let method = match bin.op {
+                BinOp::Eq(_) => quote!(eq),
+                BinOp::Ne(_) => quote!(ne),
+                BinOp::Lt(_) => quote!(lt),
+                BinOp::Le(_) => quote!(le),
+                BinOp::Gt(_) => quote!(gt),
+                BinOp::Ge(_) => quote!(ge),
+                BinOp::And(_) => quote!(and),
+                BinOp::Or(_) => quote!(or),
+                op => return quote!((#left #op #right)),
+            };
+            quote_spanned!(bin.op.span()=> (#left).#method(#right))
+        },
+        Expr::Paren(paren) => {
+            let inner = value(&paren.expr);
+            quote!((#inner))
+        },
+        Expr::Unary(unary) => {
+            let inner = value(&unary.expr);
+            let op = &unary.op;
+            quote!(#op #inner)
+        },
+        other => quote!(#other),
+    }
+}
@@ -3,11 +3,30 @@ pub mod condition;
 
 mod signal;
 mod module;
+mod constant;
+mod bits;
+mod instance;
+mod generator;
+mod param;
+mod design;
+mod bundle;
+mod memory;
+mod case;
+mod error;
 
 pub use crate::hdl::signal::Signal;
-pub use crate::hdl::module::{Module, SignalHolder};
+pub use crate::hdl::constant::{Const, Radix};
+pub use crate::hdl::instance::Instance;
+pub use crate::hdl::generator::Registry;
+pub use crate::hdl::param::Param;
+pub use crate::hdl::design::Design;
+pub use crate::hdl::bundle::Bundle;
+pub use crate::hdl::case::{Case, CaseKind, Qualifier};
+pub use crate::hdl::error::KungError;
+pub use crate::hdl::module::{Direction, Module, ModuleId, Scope, SignalHolder, VModule};
 
 use duplicate::duplicate;
+use num::BigInt;
 
 pub trait Synth {
     fn synth(&self) -> String;
@@ -15,33 +34,115 @@ pub trait Synth {
 
 pub trait Operand {
     fn repr(&self) -> String;
+
+    /// Width of the value in bits.
+    fn width(&self) -> u32;
+
+    fn is_signed(&self) -> bool {
+        false
+    }
+
+    /// Adds every signal used by the operand to `signals`.
+    fn collect_signals(&self, _signals: &mut Vec<Signal>) {}
+
+    /// The signal itself if the operand is just a signal.
+    fn as_signal(&self) -> Option<Signal> {
+        None
+    }
+
+    /// Whether `repr` already encloses the whole expression in parentheses,
+    /// like `(a + b)`.
+    fn is_parenthesized(&self) -> bool {
+        false
+    }
+
+    /// Whether the operand has a width in SystemVerilog, which unsized
+    /// integers like `1` don't.
+    fn is_sized(&self) -> bool {
+        true
+    }
+
+    /// Value of the operand if it is a constant.
+    fn const_value(&self) -> Option<BigInt> {
+        None
+    }
 }
 
-#[duplicate(tt; [u32]; [i32]; [String])]
+#[duplicate(tt; [u32]; [i32])]
 impl Operand for tt {
     fn repr(&self) -> String {
-        return self.to_string()
+        self.to_string()
+    }
+
+    // plain integers are unsized, so they take just the bits needed to hold them
+    fn width(&self) -> u32 {
+        let val = *self as i64;
+        if val < 0 {
+            65 - val.leading_ones()
+        } else {
+            (64 - val.leading_zeros()).max(1)
+        }
+    }
+
+    // unsized decimal literals are signed in SystemVerilog
+    fn is_signed(&self) -> bool {
+        true
+    }
+
+    fn is_sized(&self) -> bool {
+        false
+    }
+
+    fn const_value(&self) -> Option<BigInt> {
+        Some(BigInt::from(*self))
+    }
+}
+
+impl Operand for String {
+    fn repr(&self) -> String {
+        self.to_string()
+    }
+
+    // raw text, the width is unknown
+    fn width(&self) -> u32 {
+        0
     }
 }
 
+/// Continuous assign in the module of the destination: `comb!(a := b + c)`.
+/// Evaluates to a `KungError` if the widths differ or the destination is
+/// already driven.
+///
+/// The module is looked up in a registry of the current thread, see
+/// `Module::record`, so the macro has to run on the thread that created it.
 #[macro_export]
 macro_rules! comb {
-    ($a:ident := $e:expr) => {{
-        {
-            use std::ptr;
-
-            unsafe {
-                //we expect that module lives longer than the signal anyway
-                if let Some(sigmod) = $a.module {
-                    let mut uninit: std::mem::MaybeUninit<Module> = std::mem::MaybeUninit::uninit();
-                    let ptr = uninit.as_mut_ptr();
-                    ptr::write(ptr, ptr::read(&*sigmod));
-                    *ptr += expr::Assign::new(&$a, $e);
-                } else {
-                    panic!("signal doesn't have associated module")
-                }
-            }
-            println!("{} = {}", stringify!{$a}, stringify!{$e});
-        }
-    }};
+    ($a:ident := $e:expr) => {
+        $crate::hdl::expr::Assign::try_new(&$a, $crate::hdl::expr::Op::from($e))
+            .and_then($crate::hdl::Module::record)
+    };
+}
+
+/// Register assigned on the rising edge of a clock in the module of the
+/// destination: `seq!(clk, q <= d)`, or with a reset
+/// `seq!(clk, Reset::AsyncLow(rst_n), q <= d)`. Evaluates to a `KungError`
+/// like `comb!`, and like it only works on the thread of the module.
+#[macro_export]
+macro_rules! seq {
+    ($clk:expr, $q:ident <= $e:expr) => {
+        $crate::hdl::expr::Assign::try_new(&$q, $crate::hdl::expr::Op::from($e))
+            .and_then(|assign| $crate::hdl::Module::record_seq(&$clk, None, assign))
+    };
+    ($clk:expr, $rst:expr, $q:ident <= $e:expr) => {
+        $crate::hdl::expr::Assign::try_new(&$q, $crate::hdl::expr::Op::from($e))
+            .and_then(|assign| $crate::hdl::Module::record_seq(&$clk, Some($rst), assign))
+    };
+}
+
+/// Concatenation of any operands: `cat!(a, b.slice(3, 0), c)`.
+#[macro_export]
+macro_rules! cat {
+    ($($e:expr),+ $(,)?) => {
+        $crate::hdl::expr::concat(vec![$(Box::new($e) as Box<dyn $crate::hdl::Operand>),+])
+    };
 }
@@ -0,0 +1,75 @@
+use super::{Const, Operand, Signal};
+use super::expr::{Op, concat, ternary};
+
+// bits needed to hold values from 0 to `count - 1`
+fn clog2(count: u32) -> u32 {
+    if count <= 1 {
+        0
+    } else {
+        32 - (count - 1).leading_zeros()
+    }
+}
+
+// sums the operands pairwise so the adder tree stays balanced
+fn sum_tree(mut items: Vec<Op>) -> Op {
+    while items.len() > 1 {
+        let mut next = vec![];
+        let mut iter = items.into_iter();
+
+        while let Some(a) = iter.next() {
+            match iter.next() {
+                Some(b) => next.push(a + b),
+                None => next.push(a),
+            }
+        }
+        items = next;
+    }
+    items.pop().unwrap()
+}
+
+// The helpers use every bit of the signal several times, so they only work
+// on signals, assign an expression to a signal first to use them on it.
+impl Signal {
+    /// Number of set bits.
+    pub fn popcount(self) -> Op {
+        let width = clog2(self.width() + 1);
+        let bits = (0..self.width()).map(|i| self.bit(i).zext(width)).collect();
+        sum_tree(bits)
+    }
+
+    /// Number of zero bits above the most significant set bit, the full width
+    /// if there are none.
+    pub fn leading_zeros(self) -> Op {
+        let width = clog2(self.width() + 1);
+        let mut res = Op::from(Const::new(self.width(), width));
+
+        for i in 0..self.width() {
+            let zeros = self.width() - 1 - i;
+            res = ternary(self.bit(i), Const::new(zeros, width), res);
+        }
+        res
+    }
+
+    /// Bits in reversed order, the lsb becomes the msb.
+    pub fn reverse_bits(self) -> Op {
+        concat((0..self.width()).map(|i| Box::new(self.bit(i)) as Box<dyn Operand>).collect())
+    }
+
+    /// Index of the set bit of a one-hot value.
+    pub fn onehot_to_binary(self) -> Op {
+        let width = clog2(self.width());
+        if width == 0 {
+            panic!("'{}' needs at least 2 bits to be converted from one-hot", self.name());
+        }
+
+        let bits = (0..width).rev().map(|k| {
+            let set: Vec<u32> = (0..self.width()).filter(|i| i & (1 << k) != 0).collect();
+            let mut res = self.bit(set[0]);
+            for i in &set[1..] {
+                res = res | self.bit(*i);
+            }
+            Box::new(res) as Box<dyn Operand>
+        });
+        concat(bits.collect())
+    }
+}
@@ -0,0 +1,141 @@
+use super::Signal;
+use super::expr::Op;
+use super::module::{Direction, VModule};
+
+/// Group of signals used together as ports, like a valid/ready/data stream
+/// or an AXI channel. Implementations are usually generated with `bundle!`.
+/// It is a declarative macro defining the struct rather than a derive on an
+/// existing one, since every field needs a direction and a width that a
+/// plain `Signal` field doesn't carry.
+///
+/// Directions in `layout` are the ones of the producer of the bundle, the
+/// `master` modport. A flipped bundle is the consumer, the `slave` modport.
+pub trait Bundle: Sized {
+    /// Name of the SystemVerilog interface.
+    fn name() -> &'static str;
+
+    /// Names, directions and widths of the fields.
+    fn layout() -> Vec<(&'static str, Direction, u32)>;
+
+    /// Builds the bundle from signals in the order of `layout`.
+    fn from_signals(signals: Vec<Signal>, flipped: bool) -> Self;
+
+    /// Signals of the fields in the order of `layout`.
+    fn signals(&self) -> Vec<Signal>;
+
+    fn is_flipped(&self) -> bool;
+
+    /// Same signals with every direction reversed.
+    fn flip(self) -> Self {
+        let flipped = !self.is_flipped();
+        Self::from_signals(self.signals(), flipped)
+    }
+
+    fn modport(&self) -> &'static str {
+        if self.is_flipped() { "slave" } else { "master" }
+    }
+
+    /// Fields with their directions, reversed if the bundle is flipped.
+    fn fields(&self) -> Vec<(&'static str, Direction, Signal)> {
+        Self::layout().into_iter()
+            .zip(self.signals())
+            .map(|((name, dir, _), sig)| {
+                (name, if self.is_flipped() { dir.flip() } else { dir }, sig)
+            })
+            .collect()
+    }
+
+    /// Connections binding every field to the bundle port `port` of `child`,
+    /// for `Module::instance`.
+    fn connect(&self, child: &VModule, port: &str) -> Vec<(String, Op)> {
+        let sep = if child.borrow().interface(port).is_some() { "." } else { "_" };
+        Self::layout().into_iter()
+            .zip(self.signals())
+            .map(|((name, _, _), sig)| (format!("{}{}{}", port, sep, name), sig.into()))
+            .collect()
+    }
+
+    /// Declaration of the interface with its `master` and `slave` modports.
+    fn interface() -> String {
+        let layout = Self::layout();
+        let mut s = format!("interface {};\n", Self::name());
+
+        for (name, _, width) in &layout {
+            s.push_str(&Signal::new(name, *width).def());
+            s.push_str(";\n");
+        }
+        for (modport, flip) in &[("master", false), ("slave", true)] {
+            let fields: Vec<String> = layout.iter()
+                .map(|(name, dir, _)| {
+                    let dir = if *flip { dir.flip() } else { *dir };
+                    format!("{} {}", dir.keyword(), name)
+                })
+                .collect();
+            s.push_str(&format!("modport {}({});\n", modport, fields.join(", ")));
+        }
+        s.push_str("endinterface\n");
+        s
+    }
+}
+
+/// Instance of an interface inside a module, either a port or a local
+/// `name();` instance.
+pub(crate) struct InterfaceDecl {
+    pub(crate) name: String,
+    pub(crate) kind: &'static str,
+    pub(crate) definition: String,
+    pub(crate) modport: Option<&'static str>,
+    pub(crate) fields: Vec<Signal>,
+}
+
+/// Defines a struct of signals implementing `Bundle`, each field is declared
+/// with the direction it has for the producer and its width:
+///
+/// ```
+/// kung::bundle! {
+///     pub struct Stream {
+///         valid: output 1,
+///         ready: input 1,
+///         data: output 8,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! bundle {
+    (@dir input) => { $crate::hdl::Direction::Input };
+    (@dir output) => { $crate::hdl::Direction::Output };
+
+    ($vis:vis struct $name:ident { $($field:ident : $dir:ident $width:expr),+ $(,)? }) => {
+        #[derive(Clone, Copy)]
+        $vis struct $name {
+            $(pub $field: $crate::hdl::Signal,)+
+            flipped: bool,
+        }
+
+        impl $crate::hdl::Bundle for $name {
+            fn name() -> &'static str {
+                stringify!($name)
+            }
+
+            fn layout() -> Vec<(&'static str, $crate::hdl::Direction, u32)> {
+                vec![$((stringify!($field), $crate::bundle!(@dir $dir), $width)),+]
+            }
+
+            fn from_signals(signals: Vec<$crate::hdl::Signal>, flipped: bool) -> Self {
+                let mut signals = signals.into_iter();
+                $name {
+                    $($field: signals.next().expect("not enough signals for the bundle"),)+
+                    flipped,
+                }
+            }
+
+            fn signals(&self) -> Vec<$crate::hdl::Signal> {
+                vec![$(self.$field),+]
+            }
+
+            fn is_flipped(&self) -> bool {
+                self.flipped
+            }
+        }
+    };
+}
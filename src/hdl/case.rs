@@ -0,0 +1,210 @@
+use super::{Operand, Signal, Synth};
+use super::expr::Op;
+use super::module::Scope;
+use super::error::{Errors, KungError};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CaseKind {
+    Case,
+    /// `z` and `?` bits of the patterns match anything.
+    Casez,
+    /// `x`, `z` and `?` bits of the patterns match anything.
+    Casex,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Qualifier {
+    /// Exactly one arm matches, the arms can be checked in parallel.
+    Unique,
+    /// The first matching arm wins, at least one arm matches.
+    Priority,
+}
+
+/// `case` statement of a scope, built with `Scope::switch`.
+pub struct Case {
+    expr: Op,
+    kind: CaseKind,
+    qualifier: Option<Qualifier>,
+    arms: Vec<(Vec<String>, Scope)>,
+    default: Option<Scope>,
+    sync: bool,
+    // labels that don't fit, the arms keep their own errors
+    errors: Errors,
+}
+
+impl CaseKind {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            CaseKind::Case => "case",
+            CaseKind::Casez => "casez",
+            CaseKind::Casex => "casex",
+        }
+    }
+}
+
+impl Qualifier {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Qualifier::Unique => "unique",
+            Qualifier::Priority => "priority",
+        }
+    }
+}
+
+impl Case {
+    pub(crate) fn new(expr: Op, sync: bool, errors: Errors) -> Self {
+        Case {
+            expr,
+            kind: CaseKind::Case,
+            qualifier: None,
+            arms: vec![],
+            default: None,
+            sync,
+            errors,
+        }
+    }
+
+    /// Matches with `casez`, patterns can use `?` and `z` as wildcards.
+    pub fn casez(&mut self) -> &mut Self {
+        self.kind = CaseKind::Casez;
+        self
+    }
+
+    /// Matches with `casex`, patterns can use `?`, `x` and `z` as wildcards.
+    pub fn casex(&mut self) -> &mut Self {
+        self.kind = CaseKind::Casex;
+        self
+    }
+
+    pub fn unique(&mut self) -> &mut Self {
+        self.qualifier = Some(Qualifier::Unique);
+        self
+    }
+
+    pub fn priority(&mut self) -> &mut Self {
+        self.qualifier = Some(Qualifier::Priority);
+        self
+    }
+
+    fn label(&mut self, value: &dyn Operand) -> String {
+        if value.width() > self.expr.width() {
+            self.errors.push(KungError::WidthMismatch {
+                dest: format!("case on '{}'", self.expr.repr()),
+                dest_width: self.expr.width(),
+                expr: value.repr(),
+                width: value.width(),
+            });
+        }
+        value.repr()
+    }
+
+    // reports the problem, the arm it is about is left out
+    fn invalid(&mut self, reason: String) -> &mut Self {
+        self.errors.push(KungError::InvalidStatement(reason));
+        self
+    }
+
+    fn arm<T>(&mut self, labels: Vec<String>, add_rules: T) -> &mut Self where T: Fn(&mut Scope) {
+        if self.default.is_some() {
+            return self.invalid(String::from("case arms can't follow the default arm"));
+        }
+        let mut scope = Scope::nested(self.sync, &self.errors);
+        add_rules(&mut scope);
+        self.arms.push((labels, scope));
+        self
+    }
+
+    /// Arm taken when the expression equals `value`.
+    pub fn is<V, T>(&mut self, value: V, add_rules: T) -> &mut Self
+        where V: Operand, T: Fn(&mut Scope)
+    {
+        let label = self.label(&value);
+        self.arm(vec![label], add_rules)
+    }
+
+    /// Arm taken when the expression equals any of `values`, `1, 2: ...`.
+    pub fn is_any<I, V, T>(&mut self, values: I, add_rules: T) -> &mut Self
+        where I: IntoIterator<Item = V>, V: Operand, T: Fn(&mut Scope)
+    {
+        let labels: Vec<String> = values.into_iter().map(|value| self.label(&value)).collect();
+        if labels.is_empty() {
+            return self.invalid(String::from("case arm needs at least one value"));
+        }
+        self.arm(labels, add_rules)
+    }
+
+    /// Arm with a binary wildcard pattern like `1??0`, for `casez`/`casex`.
+    pub fn pattern<T>(&mut self, pattern: &str, add_rules: T) -> &mut Self where T: Fn(&mut Scope) {
+        let wildcards = match self.kind {
+            CaseKind::Case => return self.invalid(String::from("wildcard patterns need casez or casex")),
+            CaseKind::Casez => "?zZ",
+            CaseKind::Casex => "?zZxX",
+        };
+
+        let bits: String = pattern.chars().filter(|c| *c != '_').collect();
+        if let Some(c) = bits.chars().find(|c| *c != '0' && *c != '1' && !wildcards.contains(*c)) {
+            let kind = self.kind.keyword();
+            return self.invalid(format!("'{}' is not a valid bit in the {} pattern '{}'", c, kind, pattern));
+        }
+        if bits.len() as u32 != self.expr.width() {
+            let (expr, width) = (self.expr.repr(), self.expr.width());
+            return self.invalid(format!("pattern '{}' has {} bits but '{}' is {} bits wide", pattern, bits.len(), expr, width));
+        }
+
+        self.arm(vec![format!("{}'b{}", bits.len(), pattern)], add_rules)
+    }
+
+    /// Arm taken when no other arm matches.
+    pub fn default<T>(&mut self, add_rules: T) where T: Fn(&mut Scope) {
+        if self.default.is_some() {
+            self.invalid(String::from("case already has a default arm"));
+            return;
+        }
+        let mut scope = Scope::nested(self.sync, &self.errors);
+        add_rules(&mut scope);
+        self.default = Some(scope);
+    }
+
+    pub(crate) fn scopes(&self) -> impl Iterator<Item = &Scope> {
+        self.arms.iter().map(|(_, scope)| scope).chain(self.default.iter())
+    }
+
+    pub(crate) fn take_errors(&mut self, errors: &mut Vec<KungError>) {
+        self.errors.take(errors);
+        for (_, scope) in &mut self.arms {
+            scope.take_errors(errors);
+        }
+        if let Some(scope) = &mut self.default {
+            scope.take_errors(errors);
+        }
+    }
+
+    pub(crate) fn collect_reads(&self, reads: &mut Vec<Signal>) {
+        self.expr.collect_signals(reads);
+    }
+}
+
+impl Synth for Case {
+    fn synth(&self) -> String {
+        let mut s = String::new();
+
+        if let Some(qualifier) = self.qualifier {
+            s.push_str(qualifier.keyword());
+            s.push(' ');
+        }
+        s.push_str(&format!("{} ({})\n", self.kind.keyword(), self.expr.repr()));
+
+        for (labels, scope) in &self.arms {
+            s.push_str(&format!("{}: begin\n", labels.join(", ")));
+            s.push_str(&scope.statements(self.sync));
+            s.push_str("end\n");
+        }
+        if let Some(scope) = &self.default {
+            s.push_str("default: begin\n");
+            s.push_str(&scope.statements(self.sync));
+            s.push_str("end\n");
+        }
+        s.push_str("endcase\n");
+        s
+    }
+}
@@ -1,9 +1,83 @@
 use crate::hdl::{Signal};
+use crate::hdl::expr::{Op};
 
-pub enum Conditional<'module> {
+pub enum Conditional {
     AlwaysComb,
-    Posedge(&'module Signal<'module>),
-    When(bool),
-    ElseWhen(bool),
+    /// `always_latch`, for intentional latches.
+    Latch,
+    Posedge(Signal),
+    Negedge(Signal),
+    /// Several events, `@(posedge clk or posedge rst)`.
+    Events(Vec<(Edge, Signal)>),
+    When(Op),
+    ElseWhen(Op),
     Otherwise,
 }
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edge {
+    Pos,
+    Neg,
+    /// Both edges, for dual-edge logic like DDR capture.
+    Both,
+}
+
+impl Edge {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Edge::Pos => "posedge",
+            Edge::Neg => "negedge",
+            Edge::Both => "edge",
+        }
+    }
+}
+
+impl Conditional {
+    /// Events of the sensitivity list of a clocked block, empty otherwise.
+    pub fn events(&self) -> Vec<(Edge, Signal)> {
+        match self {
+            Conditional::Posedge(sig) => vec![(Edge::Pos, *sig)],
+            Conditional::Negedge(sig) => vec![(Edge::Neg, *sig)],
+            Conditional::Events(events) => events.clone(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Reset of the registers assigned in a clocked scope.
+#[derive(Clone, Copy)]
+pub enum Reset {
+    /// Active high, sampled on the clock edge.
+    Sync(Signal),
+    /// Active low, sampled on the clock edge.
+    SyncLow(Signal),
+    /// Active high, takes effect without waiting for the clock.
+    Async(Signal),
+    /// Active low, takes effect without waiting for the clock.
+    AsyncLow(Signal),
+}
+
+impl Reset {
+    pub fn signal(&self) -> Signal {
+        match self {
+            Reset::Sync(sig) | Reset::SyncLow(sig) | Reset::Async(sig) | Reset::AsyncLow(sig) => *sig,
+        }
+    }
+
+    /// Condition under which the registers are reset, `rst` or `!rst_n`.
+    pub fn active(&self) -> String {
+        match self {
+            Reset::Sync(sig) | Reset::Async(sig) => String::from(sig.name()),
+            Reset::SyncLow(sig) | Reset::AsyncLow(sig) => format!("!{}", sig.name()),
+        }
+    }
+
+    /// Event added to the sensitivity list by asynchronous resets.
+    pub fn event(&self) -> Option<(Edge, Signal)> {
+        match self {
+            Reset::Async(sig) => Some((Edge::Pos, *sig)),
+            Reset::AsyncLow(sig) => Some((Edge::Neg, *sig)),
+            Reset::Sync(_) | Reset::SyncLow(_) => None,
+        }
+    }
+}
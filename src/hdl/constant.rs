@@ -0,0 +1,136 @@
+use num::{BigInt, BigUint, One, Signed};
+use super::{Operand};
+use super::error::{KungError, OrPanic};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Radix {
+    Bin,
+    Oct,
+    Dec,
+    Hex,
+}
+
+/// Sized constant of arbitrary width, rendered as a based literal like `32'hdeadbeef`.
+#[derive(Clone)]
+pub struct Const {
+    value: BigInt,
+    width: u32,
+    signed: bool,
+    radix: Radix,
+}
+
+impl Radix {
+    pub(crate) fn base(self) -> u32 {
+        match self {
+            Radix::Bin => 2,
+            Radix::Oct => 8,
+            Radix::Dec => 10,
+            Radix::Hex => 16,
+        }
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Radix::Bin => 'b',
+            Radix::Oct => 'o',
+            Radix::Dec => 'd',
+            Radix::Hex => 'h',
+        }
+    }
+}
+
+impl Const {
+    pub fn new<T: Into<BigUint>>(value: T, width: u32) -> Self {
+        Const::try_new(value, width).or_panic()
+    }
+
+    pub fn signed<T: Into<BigInt>>(value: T, width: u32) -> Self {
+        Const::try_signed(value, width).or_panic()
+    }
+
+    /// Same as `new`, fails if the value doesn't fit in `width` bits.
+    pub fn try_new<T: Into<BigUint>>(value: T, width: u32) -> Result<Self, KungError> {
+        Const::build(BigInt::from(value.into()), width, false)
+    }
+
+    /// Same as `signed`, fails if the value doesn't fit in `width` bits.
+    pub fn try_signed<T: Into<BigInt>>(value: T, width: u32) -> Result<Self, KungError> {
+        Const::build(value.into(), width, true)
+    }
+
+    /// Parses `digits` in the given radix (2, 8, 10 or 16), underscores are
+    /// allowed as separators like in SystemVerilog.
+    pub fn from_str_radix(digits: &str, radix: u32, width: u32) -> Self {
+        let radix = match radix {
+            2 => Radix::Bin,
+            8 => Radix::Oct,
+            10 => Radix::Dec,
+            16 => Radix::Hex,
+            _ => panic!("unsupported radix {}", radix),
+        };
+        let clean: String = digits.chars().filter(|c| *c != '_').collect();
+        let value = BigUint::parse_bytes(clean.as_bytes(), radix.base())
+            .unwrap_or_else(|| panic!("'{}' is not a valid number in radix {}", digits, radix.base()));
+
+        Const::new(value, width).radix(radix)
+    }
+
+    fn build(value: BigInt, width: u32, signed: bool) -> Result<Self, KungError> {
+        // constants are at least 1 bit wide
+        let fits = width > 0 && if signed {
+            let limit = BigInt::one() << (width - 1) as usize;
+            value >= -&limit && value < limit
+        } else {
+            !value.is_negative() && value < BigInt::one() << width as usize
+        };
+        if !fits {
+            return Err(KungError::Overflow { value: value.to_string(), width });
+        }
+
+        Ok(Const {
+            value,
+            width,
+            signed,
+            radix: Radix::Hex,
+        })
+    }
+
+    pub fn radix(mut self, radix: Radix) -> Self {
+        self.radix = radix;
+        self
+    }
+
+    pub fn value(&self) -> &BigInt {
+        &self.value
+    }
+}
+
+impl Operand for Const {
+    fn repr(&self) -> String {
+        let sign = if self.signed { "s" } else { "" };
+
+        if self.value.is_negative() && self.radix == Radix::Dec {
+            return format!("-{}'{}d{}", self.width, sign, -&self.value);
+        }
+
+        // negative values are written in two's complement
+        let bits = if self.value.is_negative() {
+            (BigInt::one() << self.width as usize) + &self.value
+        } else {
+            self.value.clone()
+        };
+        format!("{}'{}{}{}", self.width, sign, self.radix.letter(), bits.to_str_radix(self.radix.base()))
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    fn const_value(&self) -> Option<BigInt> {
+        Some(self.value.clone())
+    }
+}
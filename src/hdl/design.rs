@@ -0,0 +1,131 @@
+use super::{Synth};
+use super::module::{ModuleId, VModule};
+use super::error::{KungError, OrPanic};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Top module of a design together with everything it instantiates.
+pub struct Design {
+    top: VModule,
+}
+
+impl Design {
+    pub fn new(top: &VModule) -> Self {
+        Design {
+            top: top.clone(),
+        }
+    }
+
+    pub fn top(&self) -> &VModule {
+        &self.top
+    }
+
+    /// Every module of the hierarchy once, children before their parents.
+    pub fn modules(&self) -> Vec<VModule> {
+        self.try_modules().or_panic()
+    }
+
+    /// Same as `modules`, fails if different modules have the same name.
+    pub fn try_modules(&self) -> Result<Vec<VModule>, KungError> {
+        let mut visited = BTreeSet::new();
+        let mut order = vec![];
+        Design::visit(&self.top, &mut visited, &mut order);
+
+        let mut names: BTreeMap<String, ModuleId> = BTreeMap::new();
+        for module in &order {
+            let module = module.borrow();
+            if let Some(id) = names.insert(String::from(module.name()), module.id()) {
+                if id != module.id() {
+                    return Err(KungError::DuplicateModule(String::from(module.name())));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Names and declarations of the interfaces used by the modules.
+    pub fn interfaces(&self) -> Vec<(String, String)> {
+        let mut interfaces: Vec<(String, String)> = vec![];
+        for module in self.modules() {
+            for (kind, def) in module.borrow().interface_defs() {
+                if !interfaces.iter().any(|(name, _)| name == kind) {
+                    interfaces.push((String::from(kind), String::from(def)));
+                }
+            }
+        }
+        interfaces
+    }
+
+    fn visit(module: &VModule, visited: &mut BTreeSet<ModuleId>, order: &mut Vec<VModule>) {
+        let id = module.borrow().id();
+        if !visited.insert(id) {
+            return;
+        }
+
+        for instance in module.borrow().instances() {
+            Design::visit(instance.module(), visited, order);
+        }
+        order.push(module.clone());
+    }
+
+    fn filelist(&self, dir: &Path, files: &[PathBuf]) -> io::Result<PathBuf> {
+        let mut list = String::new();
+        for file in files {
+            list.push_str(&file.to_string_lossy());
+            list.push('\n');
+        }
+
+        let path = dir.join(format!("{}.f", self.top.borrow().name()));
+        fs::write(&path, list)?;
+        Ok(path)
+    }
+
+    // memory contents are loaded by the simulator, they are not in the filelist
+    fn write_mem_files(&self, dir: &Path) -> io::Result<()> {
+        for module in self.modules() {
+            for (path, contents) in module.borrow().mem_files() {
+                fs::write(dir.join(path), contents)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the whole design to `<top>.sv` in `dir` and returns the path of
+    /// the `<top>.f` filelist pointing to it. Memory files go to `dir` too.
+    pub fn write_file(&self, dir: &Path) -> io::Result<PathBuf> {
+        self.write_mem_files(dir)?;
+        let path = dir.join(format!("{}.sv", self.top.borrow().name()));
+        fs::write(&path, self.synth())?;
+        self.filelist(dir, &[path])
+    }
+
+    /// Writes every interface and module to its own `<name>.sv` in `dir` and
+    /// returns the path of the `<top>.f` filelist listing them in dependency
+    /// order.
+    pub fn write_files(&self, dir: &Path) -> io::Result<PathBuf> {
+        self.write_mem_files(dir)?;
+        let mut files = vec![];
+        for (name, def) in self.interfaces() {
+            let path = dir.join(format!("{}.sv", name));
+            fs::write(&path, def)?;
+            files.push(path);
+        }
+        for module in self.modules() {
+            let path = dir.join(format!("{}.sv", module.borrow().name()));
+            fs::write(&path, module.synth())?;
+            files.push(path);
+        }
+        self.filelist(dir, &files)
+    }
+}
+
+impl Synth for Design {
+    fn synth(&self) -> String {
+        let interfaces = self.interfaces().into_iter().map(|(_, def)| def);
+        let modules = self.modules().into_iter().map(|m| m.synth());
+        let all: Vec<String> = interfaces.chain(modules).collect();
+        all.join("\n")
+    }
+}
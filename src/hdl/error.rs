@@ -0,0 +1,146 @@
+use std::error::Error;
+use std::fmt;
+use super::Radix;
+
+/// Problem found while building a design. The `try_*` builders return it,
+/// the other builders panic with its message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KungError {
+    /// Name longer than the 64 bytes signals and parameters can hold.
+    NameTooLong(String),
+    /// Signal declared without any bits.
+    ZeroWidth(String),
+    /// Signal, port, parameter or instance declared twice in a module.
+    Duplicate { kind: &'static str, name: String, module: String },
+    /// Signal driven by more than one assign, block or instance.
+    DuplicateDriver { signal: String, module: String },
+    /// Destination assigned twice in the same scope.
+    DuplicateAssign(String),
+    /// Statement that can't be added where it was, like `otherwise` without
+    /// a `when` before it, with the reason.
+    InvalidStatement(String),
+    /// Value of `width` bits assigned or connected to `dest`, which describes
+    /// the destination, e.g. `'a'` or `port 'a' of 'adder'`.
+    WidthMismatch { dest: String, dest_width: u32, expr: String, width: u32 },
+    /// Signal that doesn't belong to a module that still exists.
+    UnknownSignal(String),
+    /// Module of the signal is already borrowed, e.g. while building one of
+    /// its scopes.
+    ModuleInUse(String),
+    /// Input driven inside its own module, by an assign, a block or the
+    /// output of `instance`.
+    InvalidDirection { signal: String, module: String, instance: Option<String> },
+    /// Memory written as a whole instead of word by word.
+    WholeMemory(String),
+    /// Signal used as a memory without being one.
+    NotMemory(String),
+    /// Memory file in a radix `$readmemh`/`$readmemb` can't read.
+    MemFileRadix(Radix),
+    /// More contents than words in the memory or the ROM.
+    TooManyWords { memory: String, words: usize, depth: u64 },
+    /// Word of the contents of a memory or a ROM wider than its words.
+    WordTooWide { memory: String, index: usize, value: String, width: u32 },
+    /// Always block of `module` sensitive to no event.
+    NoEvents(String),
+    /// Constant that doesn't fit in its width.
+    Overflow { value: String, width: u32 },
+    UnknownPort { module: String, port: String },
+    UnknownParam { module: String, param: String },
+    Unconnected { module: String, port: String, instance: String },
+    /// Port of `module` bound to something it can't be bound to.
+    InvalidConnection { module: String, port: String, reason: String },
+    /// Module instantiating itself.
+    SelfInstance(String),
+    /// Different modules of a design with the same name.
+    DuplicateModule(String),
+}
+
+impl fmt::Display for KungError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use KungError::*;
+
+        match self {
+            NameTooLong(name) => write!(f, "name '{}' is longer than 64 bytes", name),
+            ZeroWidth(name) => write!(f, "signal '{}' can't be 0 bits wide", name),
+            Duplicate { kind, name, module } =>
+                write!(f, "{} with name '{}' already defined in module '{}'", kind, name, module),
+            DuplicateDriver { signal, module } => write!(f, "'{}' is already driven in module '{}'", signal, module),
+            DuplicateAssign(target) => write!(f, "assign with destination '{}' already defined in this scope", target),
+            InvalidStatement(reason) => write!(f, "{}", reason),
+            WidthMismatch { dest, dest_width, expr, width } =>
+                write!(f, "width mismatch: {} is {} bits wide but '{}' is {} bits, use resize() to convert",
+                    dest, dest_width, expr, width),
+            UnknownSignal(name) => write!(f, "signal '{}' doesn't belong to an existing module", name),
+            ModuleInUse(name) => write!(f, "module of signal '{}' is in use", name),
+            InvalidDirection { signal, module, instance: Some(instance) } =>
+                write!(f, "instance '{}' drives input '{}' of module '{}'", instance, signal, module),
+            InvalidDirection { signal, module, instance: None } =>
+                write!(f, "input '{}' of module '{}' can't be driven", signal, module),
+            WholeMemory(name) => write!(f, "memory '{}' can only be written by word, use Assign::indexed()", name),
+            NotMemory(name) => write!(f, "'{}' is not a memory", name),
+            MemFileRadix(radix) => write!(f, "memory files can only be hexadecimal or binary, not {:?}", radix),
+            TooManyWords { memory, words, depth } => write!(f, "{} words don't fit in '{}' of {} words", words, memory, depth),
+            WordTooWide { memory, index, value, width } =>
+                write!(f, "word {} of '{}' ({}) doesn't fit in {} bits", index, memory, value, width),
+            NoEvents(module) => write!(f, "block in module '{}' needs at least one event", module),
+            Overflow { value, width } => write!(f, "constant {} doesn't fit in {} bits", value, width),
+            UnknownPort { module, port } => write!(f, "'{}' has no port named '{}'", module, port),
+            UnknownParam { module, param } => write!(f, "'{}' has no parameter named '{}'", module, param),
+            Unconnected { module, port, instance } =>
+                write!(f, "port '{}' of '{}' is not connected in instance '{}'", port, module, instance),
+            InvalidConnection { module, port, reason } => write!(f, "port '{}' of '{}' {}", port, module, reason),
+            SelfInstance(module) => write!(f, "module '{}' can't instantiate itself", module),
+            DuplicateModule(name) => write!(f, "design has different modules named '{}'", name),
+        }
+    }
+}
+
+impl Error for KungError {}
+
+/// Turns the errors of the `try_*` builders back into panics for the
+/// builders that don't return them.
+pub(crate) trait OrPanic<T> {
+    fn or_panic(self) -> T;
+}
+
+impl<T> OrPanic<T> for Result<T, KungError> {
+    fn or_panic(self) -> T {
+        self.unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<T> OrPanic<T> for Result<T, Vec<KungError>> {
+    fn or_panic(self) -> T {
+        self.unwrap_or_else(|errors| panic!("{}", errors[0]))
+    }
+}
+
+/// Problems found while adding the rules of a scope. Scopes of the `try_*`
+/// builders keep them to return them all at the end, the others panic on
+/// the first one.
+#[derive(Default)]
+pub(crate) struct Errors(Option<Vec<KungError>>);
+
+impl Errors {
+    pub(crate) fn collecting() -> Self {
+        Errors(Some(vec![]))
+    }
+
+    /// No errors yet, kept or not like `self`, for a nested scope.
+    pub(crate) fn nested(&self) -> Self {
+        Errors(self.0.as_ref().map(|_| vec![]))
+    }
+
+    pub(crate) fn push(&mut self, err: KungError) {
+        match &mut self.0 {
+            Some(errors) => errors.push(err),
+            None => panic!("{}", err),
+        }
+    }
+
+    pub(crate) fn take(&mut self, errors: &mut Vec<KungError>) {
+        if let Some(own) = &mut self.0 {
+            errors.append(own);
+        }
+    }
+}
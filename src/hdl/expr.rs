@@ -1,6 +1,8 @@
-use crate::hdl::{Operand, Signal};
-//use std::ops::{Add, Sub, Shl, Shr, Mul, Div, BitAnd, BitOr, BitXor, Not};
-//use duplicate::duplicate;
+use crate::hdl::{Const, Operand, Param, Signal};
+use crate::hdl::error::{KungError, OrPanic};
+use std::ops::{Add, Sub, Shl, Shr, Mul, Div, BitAnd, BitOr, BitXor, Not};
+use duplicate::duplicate;
+use num::{BigInt, Signed};
 
 type OpOperand = Box<dyn Operand>;
 
@@ -8,161 +10,715 @@ pub struct Op {
     pub a: OpOperand,
     pub b: Option<OpOperand>,
     pub op: String,
+
+    width: u32,
+    signed: bool,
+}
+
+/// Explicit width conversion, rendered as a SystemVerilog size cast.
+pub struct Resize {
+    a: OpOperand,
+    width: u32,
+}
+
+/// Signedness conversion, `$signed(a)` or `$unsigned(a)`.
+pub struct Cast {
+    a: OpOperand,
+    signed: bool,
+}
+
+/// Part select of a signal, `a[msb:lsb]`, or a single bit `a[i]`.
+pub struct Slice {
+    a: Signal,
+    msb: u32,
+    lsb: u32,
 }
 
-pub struct Assign<'module> {
+/// Dynamic select of a memory word or an array element, `a[index]`.
+pub struct Index {
+    a: Signal,
+    index: OpOperand,
+}
+
+/// Conditional operator, `sel ? a : b`.
+pub struct Mux {
+    sel: OpOperand,
+    a: OpOperand,
+    b: OpOperand,
+}
+
+/// Concatenation, `{a, b, c}`.
+pub struct Concat {
+    parts: Vec<OpOperand>,
+}
+
+/// Replication, `{n{a}}`.
+pub struct Replicate {
+    a: OpOperand,
+    count: u32,
+}
+
+pub struct Assign {
     pub op: Op,
-    pub dest: &'module Signal<'module>,
+    pub dest: Signal,
+    pub index: Option<Op>,
+}
+
+// representation of the operand in parentheses, added only if the node
+// doesn't render its own
+pub(crate) fn parens(a: &dyn Operand) -> String {
+    if a.is_parenthesized() {
+        a.repr()
+    } else {
+        format!("({})", a.repr())
+    }
+}
+
+// memories can only be used word by word, `mem[addr]`, SystemVerilog
+// doesn't allow reading them as a whole
+fn check_word(a: &dyn Operand) {
+    if let Some(mem) = a.as_signal().filter(|sig| sig.words().is_some()) {
+        panic!("memory '{}' can only be read by word, use index()", mem.name());
+    }
 }
 
 impl Op {
     pub fn new_unary(a: OpOperand, op: &str) -> Self {
+        check_word(&*a);
+        let (width, signed) = match op {
+            "!" | "&" | "|" | "^" | "~&" | "~|" | "~^" => (1, false),
+            _ => (a.width(), a.is_signed()),
+        };
+
         Self {
-            a: a,
+            a,
             b: None,
             op: String::from(op),
+            width,
+            signed,
         }
     }
     pub fn new(a: OpOperand, b: OpOperand, op: &str) -> Self {
+        check_word(&*a);
+        check_word(&*b);
+        // the result is signed only if both operands are, as in SystemVerilog
+        let (width, signed) = match op {
+            "==" | "!=" | "<" | "<=" | ">" | ">=" | "&&" | "||" => (1, false),
+            "*" => (a.width() + b.width(), a.is_signed() && b.is_signed()),
+            "/" => (a.width(), a.is_signed() && b.is_signed()),
+            "<<" | ">>" | ">>>" => (a.width(), a.is_signed()),
+            _ => (a.width().max(b.width()), a.is_signed() && b.is_signed()),
+        };
+
+        // shifting a signed value right keeps the sign, like in Rust
+        let op = if op == ">>" && signed { ">>>" } else { op };
+
         Self {
-            a: a,
+            a,
             b: Some(b),
             op: String::from(op),
+            width,
+            signed,
+        }
+    }
+
+    /// Makes an `Op` out of an operand that renders itself, like a cast.
+    pub fn wrap(a: OpOperand) -> Self {
+        check_word(&*a);
+        let width = a.width();
+        let signed = a.is_signed();
+
+        Self {
+            a,
+            b: None,
+            op: String::new(),
+            width,
+            signed,
         }
     }
 }
 
 impl Operand for Op {
     fn repr(&self) -> String {
-        let s = match &self.b {
+        match &self.b {
             Some(val) => format!("({} {} {})", &self.a.repr(), &self.op, &val.repr()),
+            None if self.op.is_empty() => self.a.repr(),
             None => format!("({}{})", &self.op, &self.a.repr()),
-        };
-        return s;
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        self.a.collect_signals(signals);
+        if let Some(b) = &self.b {
+            b.collect_signals(signals);
+        }
+    }
+
+    fn as_signal(&self) -> Option<Signal> {
+        match &self.b {
+            None if self.op.is_empty() => self.a.as_signal(),
+            _ => None,
+        }
+    }
+
+    fn is_parenthesized(&self) -> bool {
+        match &self.b {
+            None if self.op.is_empty() => self.a.is_parenthesized(),
+            _ => true,
+        }
+    }
+
+    fn const_value(&self) -> Option<BigInt> {
+        match &self.b {
+            None if self.op.is_empty() => self.a.const_value(),
+            _ => None,
+        }
     }
 }
 
-impl<'module> Assign<'module> {
-    pub fn new(dest: &'module Signal, op: Op) -> Self {
-        Assign {
-            op: op,
-            dest,
+impl Operand for Resize {
+    fn repr(&self) -> String {
+        format!("{}'{}", self.width, parens(&*self.a))
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn is_signed(&self) -> bool {
+        self.a.is_signed()
+    }
+
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        self.a.collect_signals(signals);
+    }
+}
+
+impl Operand for Cast {
+    fn repr(&self) -> String {
+        let func = if self.signed { "$signed" } else { "$unsigned" };
+        format!("{}{}", func, parens(&*self.a))
+    }
+
+    fn width(&self) -> u32 {
+        self.a.width()
+    }
+
+    fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        self.a.collect_signals(signals);
+    }
+}
+
+impl Operand for Slice {
+    fn repr(&self) -> String {
+        if self.msb == self.lsb {
+            format!("{}[{}]", self.a.repr(), self.msb)
+        } else {
+            format!("{}[{}:{}]", self.a.repr(), self.msb, self.lsb)
         }
     }
 
-    pub fn synth(&self, nonblocking: bool) -> String {
-        let assign_op = if nonblocking { "<=" } else { "=" };
-        format!("{} {} {};", &self.dest.repr(), assign_op, &self.op.repr())
+    fn width(&self) -> u32 {
+        self.msb - self.lsb + 1
+    }
+
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        signals.push(self.a);
     }
 }
 
-/*
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Add<tt> for Op {
-    type Output = Op;
+impl Operand for Index {
+    fn repr(&self) -> String {
+        format!("{}[{}]", self.a.repr(), self.index.repr())
+    }
+
+    fn width(&self) -> u32 {
+        self.a.element_width()
+    }
 
-    fn add(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, "+");
+    // only words of a memory keep the signedness, selects of a vector don't
+    fn is_signed(&self) -> bool {
+        self.a.is_signed() && self.a.words().is_some()
+    }
+
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        signals.push(self.a);
+        self.index.collect_signals(signals);
     }
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Sub<tt> for Op {
-    type Output = Op;
+impl Operand for Mux {
+    fn repr(&self) -> String {
+        format!("({} ? {} : {})", self.sel.repr(), self.a.repr(), self.b.repr())
+    }
+
+    fn width(&self) -> u32 {
+        self.a.width().max(self.b.width())
+    }
 
-    fn sub(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, "-");
+    fn is_signed(&self) -> bool {
+        self.a.is_signed() && self.b.is_signed()
+    }
+
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        self.sel.collect_signals(signals);
+        self.a.collect_signals(signals);
+        self.b.collect_signals(signals);
+    }
+
+    fn is_parenthesized(&self) -> bool {
+        true
     }
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Mul<tt> for Op {
-    type Output = Op;
+impl Operand for Concat {
+    fn repr(&self) -> String {
+        let parts: Vec<String> = self.parts.iter().map(|p| p.repr()).collect();
+        format!("{{{}}}", parts.join(", "))
+    }
+
+    fn width(&self) -> u32 {
+        self.parts.iter().map(|p| p.width()).sum()
+    }
 
-    fn mul(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, "*");
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        for part in &self.parts {
+            part.collect_signals(signals);
+        }
     }
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Div<tt> for Op {
-    type Output = Op;
+impl Operand for Replicate {
+    fn repr(&self) -> String {
+        format!("{{{}{{{}}}}}", self.count, self.a.repr())
+    }
 
-    fn div(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, "/");
+    fn width(&self) -> u32 {
+        self.count * self.a.width()
+    }
+
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        self.a.collect_signals(signals);
     }
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Shl<tt> for Op {
-    type Output = Op;
+/// Concatenates `parts`, the first one ends up in the most significant bits.
+pub fn concat(parts: Vec<OpOperand>) -> Op {
+    if parts.is_empty() {
+        panic!("concatenation needs at least one operand");
+    }
+    for part in &parts {
+        check_word(&**part);
+        // the width of `{a, 1}` depends on the tool, SystemVerilog forbids it
+        if !part.is_sized() {
+            panic!("'{}' has no width in the concatenation, use a Const", part.repr());
+        }
+    }
+    Op::wrap(Box::new(Concat { parts }))
+}
+
+/// `sel ? a : b`
+pub fn ternary<S, A, B>(sel: S, a: A, b: B) -> Op
+    where S: Operand + 'static, A: Operand + 'static, B: Operand + 'static
+{
+    check_word(&sel);
+    check_word(&a);
+    check_word(&b);
+    Op::wrap(Box::new(Mux {
+        sel: Box::new(sel),
+        a: Box::new(a),
+        b: Box::new(b),
+    }))
+}
+
+/// Selects `options[sel]` with a balanced tree of ternaries, one level per
+/// bit of `sel`. If there are less than `2^width` options, out of range
+/// values of `sel` select one of the last options.
+pub fn mux<I, T>(sel: Signal, options: I) -> Op
+    where I: IntoIterator<Item = T>, T: Operand + 'static
+{
+    let mut level: Vec<OpOperand> = options.into_iter()
+        .map(|o| Box::new(o) as OpOperand)
+        .collect();
+    for option in &level {
+        check_word(&**option);
+    }
+
+    if level.is_empty() {
+        panic!("mux needs at least one option");
+    }
+    if sel.width() < 64 && level.len() as u64 > 1u64 << sel.width() {
+        panic!("{} options can't be selected with '{}' of width {}", level.len(), sel.name(), sel.width());
+    }
+
+    let mut bit = 0;
+    while level.len() > 1 {
+        let mut next: Vec<OpOperand> = vec![];
+        let mut items = level.into_iter();
 
-    fn shl(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, "<<");
+        while let Some(even) = items.next() {
+            match items.next() {
+                Some(odd) => next.push(Box::new(Op::wrap(Box::new(Mux {
+                    sel: Box::new(sel.bit(bit)),
+                    a: odd,
+                    b: even,
+                })))),
+                None => next.push(even),
+            }
+        }
+        level = next;
+        bit += 1;
     }
+
+    Op::wrap(level.pop().unwrap())
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Shr<tt> for Op {
-    type Output = Op;
+fn check_slice(a: &dyn Operand, msb: u32, lsb: u32) {
+    check_word(a);
+    if msb >= a.width() {
+        panic!("bit {} is out of range for '{}' of width {}", msb, a.repr(), a.width());
+    }
+    if lsb > msb {
+        panic!("slice [{}:{}] of '{}' is reversed", msb, lsb, a.repr());
+    }
+}
+
+impl Signal {
+    pub fn slice(self, msb: u32, lsb: u32) -> Op {
+        check_slice(&self, msb, lsb);
+        Op::wrap(Box::new(Slice { a: self, msb, lsb }))
+    }
+
+    pub fn bit(self, index: u32) -> Op {
+        self.slice(index, index)
+    }
+
+    /// Selects the word at `index` of a memory, the element of a packed array
+    /// or a single bit of a vector, the index can be any expression. Constant
+    /// indexes are checked against the number of elements.
+    pub fn index<T: Operand + 'static>(self, index: T) -> Op {
+        if let Some(value) = index.const_value() {
+            let count = self.words().unwrap_or(self.width() / self.element_width());
+            if value.is_negative() || value >= BigInt::from(count) {
+                panic!("index {} is out of range for '{}' of {} elements", value, self.name(), count);
+            }
+        }
+        Op::wrap(Box::new(Index { a: self, index: Box::new(index) }))
+    }
+}
+
+impl Op {
+    // SystemVerilog can only select bits of a variable, so an expression is
+    // shifted down and truncated instead
+    pub fn slice(self, msb: u32, lsb: u32) -> Op {
+        check_slice(&self, msb, lsb);
+        let shifted = if lsb == 0 { self } else { self >> lsb };
+        // part selects are always unsigned
+        let shifted = if shifted.is_signed() { shifted.as_unsigned() } else { shifted };
+        shifted.resized(msb - lsb + 1)
+    }
+
+    pub fn bit(self, index: u32) -> Op {
+        self.slice(index, index)
+    }
+}
+
+#[duplicate(tt; [Signal]; [Const])]
+impl From<tt> for Op {
+    fn from(item: tt) -> Self {
+        Op::wrap(Box::new(item))
+    }
+}
+
+/// `Op` built from any value that converts to one, `Op::from` without a
+/// conversion of an `Op` to itself. Used by the code of `hdl!`.
+pub fn op<T: Into<Op>>(value: T) -> Op {
+    value.into()
+}
+
+impl Assign {
+    pub fn new(dest: &Signal, op: Op) -> Self {
+        Assign::try_new(dest, op).or_panic()
+    }
+
+    /// Same as `new`, fails if the widths differ.
+    pub fn try_new(dest: &Signal, op: Op) -> Result<Self, KungError> {
+        let assign = Assign {
+            op,
+            dest: *dest,
+            index: None,
+        };
+        assign.check()?;
+        Ok(assign)
+    }
+
+    /// Fails if the destination can't hold the value.
+    fn check(&self) -> Result<(), KungError> {
+        let (dest, op) = (&self.dest, &self.op);
+        if self.index.is_none() && dest.words().is_some() {
+            return Err(KungError::WholeMemory(String::from(dest.name())));
+        }
+        let width = if self.index.is_some() { dest.element_width() } else { dest.width() };
+        if width != op.width() {
+            let dest = match self.index {
+                Some(_) => format!("each element of '{}'", dest.name()),
+                None => format!("'{}'", dest.name()),
+            };
+            return Err(KungError::WidthMismatch { dest, dest_width: width, expr: op.repr(), width: op.width() });
+        }
+        Ok(())
+    }
+
+    /// Assignment to a memory word or an array element, `dest[index] = op`.
+    pub fn indexed(dest: &Signal, index: Op, op: Op) -> Self {
+        Assign::try_indexed(dest, index, op).or_panic()
+    }
+
+    /// Same as `indexed`, fails if the widths differ.
+    pub fn try_indexed(dest: &Signal, index: Op, op: Op) -> Result<Self, KungError> {
+        let assign = Assign {
+            op,
+            dest: *dest,
+            index: Some(index),
+        };
+        assign.check()?;
+        Ok(assign)
+    }
+
+    /// Left side of the assignment as it is rendered.
+    pub fn target(&self) -> String {
+        match &self.index {
+            Some(index) => format!("{}[{}]", self.dest.repr(), index.repr()),
+            None => self.dest.repr(),
+        }
+    }
 
-    fn shr(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, ">>");
+    pub fn synth(&self, nonblocking: bool) -> String {
+        let assign_op = if nonblocking { "<=" } else { "=" };
+        format!("{} {} {};", &self.target(), assign_op, &self.op.repr())
     }
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl BitAnd<tt> for Op {
+#[duplicate(
+    trait_name  method  op;
+    [Add]       [add]       ["+"];
+    [Sub]       [sub]       ["-"];
+    [Mul]       [mul]       ["*"];
+    [Div]       [div]       ["/"];
+    [Shl]       [shl]       ["<<"];
+    [Shr]       [shr]       [">>"];
+    [BitAnd]    [bitand]    ["&"];
+    [BitOr]     [bitor]     ["|"];
+    [BitXor]    [bitxor]    ["^"];
+)]
+#[duplicate(tt; [Signal]; [Op]; [u32]; [i32]; [Const]; [Param])]
+impl trait_name<tt> for Op {
     type Output = Op;
 
-    fn bitand(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, "&");
+    fn method(self, other: tt) -> Self::Output {
+        Op::new(Box::new(self), Box::new(other), op)
     }
 }
 
-impl Not for Op {
+// constants on the left side, e.g. `1 + a`
+#[duplicate(
+    trait_name  method  op;
+    [Add]       [add]       ["+"];
+    [Sub]       [sub]       ["-"];
+    [Mul]       [mul]       ["*"];
+    [Div]       [div]       ["/"];
+    [BitAnd]    [bitand]    ["&"];
+    [BitOr]     [bitor]     ["|"];
+    [BitXor]    [bitxor]    ["^"];
+)]
+#[duplicate(lhs; [u32]; [i32]; [Const]; [Param])]
+#[duplicate(tt; [Signal]; [Op])]
+impl trait_name<tt> for lhs {
     type Output = Op;
 
-    fn not(self) -> Self::Output {
-        return Op::op_based_unary(self, "~");
+    fn method(self, other: tt) -> Self::Output {
+        Op::new(Box::new(self), Box::new(other), op)
     }
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl BitOr<tt> for Op {
+// a shift takes the width of its left side, which unsized integers don't
+// have: `1 << sel` is 32 bits in SystemVerilog, so it needs a sized `Const`
+#[duplicate(
+    trait_name  method  op;
+    [Shl]       [shl]       ["<<"];
+    [Shr]       [shr]       [">>"];
+)]
+#[duplicate(lhs; [Const]; [Param])]
+#[duplicate(tt; [Signal]; [Op])]
+impl trait_name<tt> for lhs {
     type Output = Op;
 
-    fn bitor(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, "|");
+    fn method(self, other: tt) -> Self::Output {
+        Op::new(Box::new(self), Box::new(other), op)
     }
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl BitXor<tt> for Op {
+impl Not for Op {
     type Output = Op;
 
-    fn bitxor(self, other: tt) -> Self::Output {
-        return Op::op_based(self, other, "^");
+    fn not(self) -> Self::Output {
+        Op::new_unary(Box::new(self), "~")
     }
 }
 
-impl From<bool> for Op {
-    fn from(item: bool) -> Self {
-        let last_condition = Condition::pop_last();
-        if let Some(cond) = last_condition {
-            // do nothing
-            Op {
-                a: Box::new(cond),
-                b: None,
-                op: String::from(""),
-            }
+// comparisons can't go through PartialEq/PartialOrd since those have to
+// return bool, so they are plain builders returning a 1-bit expression
+#[duplicate(
+    method  op;
+    [eq]    ["=="];
+    [ne]    ["!="];
+    [lt]    ["<"];
+    [le]    ["<="];
+    [gt]    [">"];
+    [ge]    [">="];
+)]
+#[duplicate(tt; [Signal]; [Op])]
+impl tt {
+    pub fn method<T: Operand + 'static>(self, other: T) -> Op {
+        Op::new(Box::new(self), Box::new(other), op)
+    }
+}
+
+#[duplicate(tt; [Signal]; [Op])]
+impl tt {
+    fn resized(self, width: u32) -> Op {
+        check_word(&self);
+        Op::wrap(Box::new(Resize {
+            a: Box::new(self),
+            width,
+        }))
+    }
+
+    fn cast(self, signed: bool) -> Op {
+        check_word(&self);
+        Op::wrap(Box::new(Cast {
+            a: Box::new(self),
+            signed,
+        }))
+    }
+
+    /// `$signed(self)`
+    pub fn as_signed(self) -> Op {
+        self.cast(true)
+    }
+
+    /// `$unsigned(self)`
+    pub fn as_unsigned(self) -> Op {
+        self.cast(false)
+    }
+
+    /// Converts to `width` bits, truncating or extending as needed. Signed
+    /// values are sign-extended.
+    pub fn resize(self, width: u32) -> Op {
+        self.resized(width)
+    }
+
+    /// Drops the upper bits, `width` can't be larger than the current width.
+    pub fn trunc(self, width: u32) -> Op {
+        if width > self.width() {
+            panic!("can't truncate '{}' of width {} to {} bits", self.repr(), self.width(), width);
+        }
+        self.resized(width)
+    }
+
+    /// Zero-extends to `width` bits, `width` can't be smaller than the current width.
+    pub fn zext(self, width: u32) -> Op {
+        if width < self.width() {
+            panic!("can't extend '{}' of width {} to {} bits", self.repr(), self.width(), width);
+        }
+        if self.is_signed() {
+            self.as_unsigned().resized(width)
         } else {
-            Op {
-                a: Box::new(item as u32),
-                b: None,
-                op: String::from(""),
-            }
+            self.resized(width)
         }
     }
+
+    /// Sign-extends to `width` bits, `width` can't be smaller than the current width.
+    pub fn sext(self, width: u32) -> Op {
+        if width < self.width() {
+            panic!("can't extend '{}' of width {} to {} bits", self.repr(), self.width(), width);
+        }
+        if self.is_signed() {
+            self.resized(width)
+        } else {
+            self.as_signed().resized(width)
+        }
+    }
+
+    /// `{self, other}`, `self` ends up in the most significant bits.
+    pub fn concat<T: Operand + 'static>(self, other: T) -> Op {
+        concat(vec![Box::new(self), Box::new(other)])
+    }
+
+    /// `{count{self}}`
+    pub fn replicate(self, count: u32) -> Op {
+        if count == 0 {
+            panic!("can't replicate '{}' zero times", self.repr());
+        }
+        check_word(&self);
+        Op::wrap(Box::new(Replicate {
+            a: Box::new(self),
+            count,
+        }))
+    }
+
+    /// `&self`
+    pub fn reduce_and(self) -> Op {
+        Op::new_unary(Box::new(self), "&")
+    }
+
+    /// `|self`
+    pub fn reduce_or(self) -> Op {
+        Op::new_unary(Box::new(self), "|")
+    }
+
+    /// `^self`, the parity
+    pub fn reduce_xor(self) -> Op {
+        Op::new_unary(Box::new(self), "^")
+    }
+
+    /// `~&self`
+    pub fn reduce_nand(self) -> Op {
+        Op::new_unary(Box::new(self), "~&")
+    }
+
+    /// `~|self`
+    pub fn reduce_nor(self) -> Op {
+        Op::new_unary(Box::new(self), "~|")
+    }
+
+    /// `~^self`
+    pub fn reduce_xnor(self) -> Op {
+        Op::new_unary(Box::new(self), "~^")
+    }
+
+    pub fn and<T: Operand + 'static>(self, other: T) -> Op {
+        Op::new(Box::new(self), Box::new(other), "&&")
+    }
+
+    pub fn or<T: Operand + 'static>(self, other: T) -> Op {
+        Op::new(Box::new(self), Box::new(other), "||")
+    }
+
+    /// `!self`, unlike the `!` operator of Rust which builds the bitwise `~self`
+    pub fn logical_not(self) -> Op {
+        Op::new_unary(Box::new(self), "!")
+    }
 }
-*/
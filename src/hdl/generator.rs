@@ -0,0 +1,75 @@
+use super::{Synth};
+use super::module::{VModule};
+use std::collections::BTreeMap;
+
+/// Caches modules built by generator functions, so every variant of a
+/// parameterized module is built and emitted once.
+#[derive(Default)]
+pub struct Registry {
+    // variants in the order they were built, children come before the
+    // modules instantiating them
+    modules: Vec<VModule>,
+    index: BTreeMap<(String, Vec<(String, u64)>), usize>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            modules: vec![],
+            index: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the variant of `name` for `params`, calling `generator` only
+    /// the first time. The module is renamed after its parameters, for
+    /// example `adder_w8` for `("w", 8)`. The generator gets the registry to
+    /// build the modules it instantiates.
+    pub fn module<F>(&mut self, name: &str, params: &[(&str, u64)], generator: F) -> VModule
+        where F: FnOnce(&mut Registry) -> VModule
+    {
+        let params: Vec<(String, u64)> = params.iter()
+            .map(|(key, val)| (String::from(*key), *val))
+            .collect();
+        let key = (String::from(name), params);
+
+        if let Some(idx) = self.index.get(&key) {
+            return self.modules[*idx].clone();
+        }
+
+        let module = generator(self);
+        let unique = self.unique_name(&key.0, &key.1);
+        module.borrow_mut().set_name(&unique);
+
+        self.index.insert(key, self.modules.len());
+        self.modules.push(module.clone());
+        module
+    }
+
+    fn unique_name(&self, name: &str, params: &[(String, u64)]) -> String {
+        let mut base = String::from(name);
+        for (key, val) in params {
+            base.push_str(&format!("_{}{}", key, val));
+        }
+
+        let taken = |candidate: &str| self.modules.iter().any(|m| m.borrow().name() == candidate);
+        let mut unique = base.clone();
+        let mut counter = 1;
+        while taken(&unique) {
+            unique = format!("{}_{}", base, counter);
+            counter += 1;
+        }
+        unique
+    }
+
+    /// Every variant built so far, children before their parents.
+    pub fn modules(&self) -> &[VModule] {
+        &self.modules
+    }
+}
+
+impl Synth for Registry {
+    fn synth(&self) -> String {
+        let modules: Vec<String> = self.modules.iter().map(|m| m.synth()).collect();
+        modules.join("\n")
+    }
+}
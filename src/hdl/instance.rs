@@ -0,0 +1,170 @@
+use super::{Synth, Operand, Signal};
+use super::expr::{Op, parens};
+use super::module::{Direction, VModule};
+use super::error::{KungError, OrPanic};
+use std::collections::BTreeMap;
+
+/// Instantiation of a child module with its ports bound to expressions of
+/// the parent module.
+pub struct Instance {
+    name: String,
+    module: VModule,
+
+    params: Vec<(String, u64)>,
+    connections: Vec<(Direction, Signal, Op)>,
+    // interface ports of the child and the interfaces bound to them
+    interfaces: Vec<(String, String)>,
+}
+
+impl Instance {
+    /// Binds every port of `module` to the expression with the port name in
+    /// `connections`. Outputs can only be bound to signals. `params` override
+    /// parameters of the module, port widths are checked with their values.
+    pub fn new<S, I>(module: &VModule, name: &str, params: &[(&str, u64)], connections: I) -> Self
+        where S: AsRef<str>, I: IntoIterator<Item = (S, Op)>
+    {
+        Instance::try_new(module, name, params, connections).or_panic()
+    }
+
+    /// Same as `new`, fails if the connections don't match the ports.
+    pub fn try_new<S, I>(module: &VModule, name: &str, params: &[(&str, u64)], connections: I) -> Result<Self, KungError>
+        where S: AsRef<str>, I: IntoIterator<Item = (S, Op)>
+    {
+        let child = module.borrow();
+        let invalid = |port: &str, reason: String| KungError::InvalidConnection {
+            module: String::from(child.name()),
+            port: String::from(port),
+            reason,
+        };
+        let mut values: BTreeMap<&str, u64> = BTreeMap::new();
+
+        for param in child.params() {
+            values.insert(param.name(), param.value());
+        }
+        for (param, value) in params {
+            match child.params().iter().find(|p| p.name() == *param) {
+                Some(p) if !p.is_local() => values.insert(param, *value),
+                _ => return Err(KungError::UnknownParam { module: String::from(child.name()), param: String::from(*param) }),
+            };
+        }
+
+        let mut bound: BTreeMap<String, Op> = BTreeMap::new();
+
+        for (port, op) in connections {
+            let port = port.as_ref();
+            if bound.insert(String::from(port), op).is_some() {
+                return Err(invalid(port, format!("is connected twice in instance '{}'", name)));
+            }
+        }
+
+        let mut res = vec![];
+        for (dir, port) in child.ports() {
+            let op = bound.remove(port.name()).ok_or_else(|| KungError::Unconnected {
+                module: String::from(child.name()),
+                port: String::from(port.name()),
+                instance: String::from(name),
+            })?;
+
+            let width = match port.width_param() {
+                Some(param) => values.get(param).map_or(port.width(), |val| *val as u32),
+                None => port.width(),
+            };
+            if op.width() != width {
+                return Err(KungError::WidthMismatch {
+                    dest: format!("port '{}' of '{}'", port.name(), child.name()),
+                    dest_width: width,
+                    expr: op.repr(),
+                    width: op.width(),
+                });
+            }
+            if dir == Direction::Output && op.as_signal().is_none() {
+                return Err(invalid(port.name(), format!("is an output, it can only be connected to a signal, not '{}'", op.repr())));
+            }
+            res.push((dir, port, op));
+        }
+
+        let mut interfaces: Vec<(String, String)> = vec![];
+        for (_, port, op) in &res {
+            let iface = match child.interface_of(port.name()) {
+                Some(iface) => iface,
+                None => continue,
+            };
+            // the same field of an interface instance of the parent
+            let field = &port.name()[iface.name.len()..];
+            let bound_to = op.as_signal()
+                .and_then(|sig| match sig.interface() {
+                    Some(name) if sig.name()[name.len()..] == *field => Some(String::from(name)),
+                    _ => None,
+                })
+                .unwrap_or_default();
+
+            match interfaces.iter().find(|(port, _)| *port == iface.name) {
+                Some((_, other)) if *other == bound_to => {},
+                None if !bound_to.is_empty() => interfaces.push((iface.name.clone(), bound_to)),
+                _ => return Err(invalid(&iface.name, String::from("is an interface, it can only be connected to a whole interface"))),
+            }
+        }
+        // same order as the module header
+        interfaces.sort_by_key(|(port, _)| child.interfaces().iter().position(|iface| iface.name == *port));
+
+        if let Some(port) = bound.keys().next() {
+            return Err(KungError::UnknownPort { module: String::from(child.name()), port: port.clone() });
+        }
+
+        Ok(Instance {
+            name: String::from(name),
+            module: module.clone(),
+            params: params.iter().map(|(param, value)| (String::from(*param), *value)).collect(),
+            connections: res,
+            interfaces,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn module(&self) -> &VModule {
+        &self.module
+    }
+
+    /// Signals of the parent module driven by the outputs of the instance.
+    pub fn outputs(&self) -> Vec<Signal> {
+        self.connections.iter()
+            .filter(|(dir, _, _)| *dir == Direction::Output)
+            .filter_map(|(_, _, op)| op.as_signal())
+            .collect()
+    }
+
+    /// Signals of the parent module read by the inputs of the instance.
+    pub fn collect_reads(&self, reads: &mut Vec<Signal>) {
+        for (dir, _, op) in &self.connections {
+            if *dir == Direction::Input {
+                op.collect_signals(reads);
+            }
+        }
+    }
+}
+
+impl Synth for Instance {
+    fn synth(&self) -> String {
+        let mut ports: Vec<String> = self.connections.iter()
+            .filter(|(_, port, _)| port.interface().is_none())
+            .map(|(_, port, op)| format!(".{}{}", port.name(), parens(op)))
+            .collect();
+        for (port, iface) in &self.interfaces {
+            ports.push(format!(".{}({})", port, iface));
+        }
+
+        let params: Vec<String> = self.params.iter()
+            .map(|(param, value)| format!(".{}({})", param, value))
+            .collect();
+
+        let module = self.module.borrow();
+        if params.is_empty() {
+            format!("{} {} ({});\n", module.name(), self.name, ports.join(", "))
+        } else {
+            format!("{} #({}) {} ({});\n", module.name(), params.join(", "), self.name, ports.join(", "))
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use super::{Const, Operand, Radix, Signal, Synth};
+use super::error::KungError;
+use num::BigUint;
+
+/// Initial contents of a memory, loaded from a file generated next to the
+/// design or assigned word by word in an `initial` block.
+pub(crate) struct MemInit {
+    mem: Signal,
+    file: Option<(String, Radix)>,
+    words: Vec<BigUint>,
+}
+
+/// Read-only memory rendered as a `case` on the address.
+pub(crate) struct Rom {
+    data: Signal,
+    addr: Signal,
+    words: Vec<BigUint>,
+}
+
+/// Converts the words and checks that they fit in `width` bits and that there
+/// are no more than `depth` of them.
+fn words<I, T>(name: &str, width: u32, depth: u64, words: I) -> Result<Vec<BigUint>, KungError>
+    where I: IntoIterator<Item = T>, T: Into<BigUint>
+{
+    let words: Vec<BigUint> = words.into_iter().map(|word| word.into()).collect();
+    if words.len() as u64 > depth {
+        return Err(KungError::TooManyWords { memory: String::from(name), words: words.len(), depth });
+    }
+    for (index, word) in words.iter().enumerate() {
+        if word.bits() > width as usize {
+            return Err(KungError::WordTooWide { memory: String::from(name), index, value: word.to_string(), width });
+        }
+    }
+    Ok(words)
+}
+
+impl MemInit {
+    pub(crate) fn try_new<I, T>(mem: &Signal, file: Option<(&str, Radix)>, contents: I) -> Result<Self, KungError>
+        where I: IntoIterator<Item = T>, T: Into<BigUint>
+    {
+        let depth = mem.words().ok_or_else(|| KungError::NotMemory(String::from(mem.name())))?;
+        if let Some((_, radix)) = file {
+            if radix != Radix::Hex && radix != Radix::Bin {
+                return Err(KungError::MemFileRadix(radix));
+            }
+        }
+
+        Ok(MemInit {
+            mem: *mem,
+            file: file.map(|(path, radix)| (String::from(path), radix)),
+            words: words(mem.name(), mem.width(), depth as u64, contents)?,
+        })
+    }
+
+    pub(crate) fn mem(&self) -> Signal {
+        self.mem
+    }
+
+    /// Path and contents of the file read by `$readmemh`/`$readmemb`, one
+    /// zero padded word per line.
+    pub(crate) fn file(&self) -> Option<(String, String)> {
+        let (path, radix) = self.file.as_ref()?;
+        let digits = match radix {
+            Radix::Hex => self.mem.width().div_ceil(4),
+            _ => self.mem.width(),
+        } as usize;
+
+        let mut s = String::new();
+        for word in &self.words {
+            s.push_str(&format!("{:0>1$}\n", word.to_str_radix(radix.base()), digits));
+        }
+        Some((path.clone(), s))
+    }
+}
+
+impl Synth for MemInit {
+    fn synth(&self) -> String {
+        match &self.file {
+            Some((path, radix)) => {
+                let task = if *radix == Radix::Hex { "$readmemh" } else { "$readmemb" };
+                format!("initial {}(\"{}\", {});\n", task, path, self.mem.name())
+            },
+            None => {
+                let mut s = String::from("initial begin\n");
+                for (addr, word) in self.words.iter().enumerate() {
+                    let value = Const::new(word.clone(), self.mem.width()).radix(Radix::Hex);
+                    s.push_str(&format!("{}[{}] = {};\n", self.mem.name(), addr, value.repr()));
+                }
+                s.push_str("end\n");
+                s
+            },
+        }
+    }
+}
+
+impl Rom {
+    pub(crate) fn try_new<I, T>(data: &Signal, addr: &Signal, contents: I) -> Result<Self, KungError>
+        where I: IntoIterator<Item = T>, T: Into<BigUint>
+    {
+        let depth = 1u64.checked_shl(addr.width()).unwrap_or(u64::MAX);
+        Ok(Rom {
+            data: *data,
+            addr: *addr,
+            words: words(data.name(), data.width(), depth, contents)?,
+        })
+    }
+
+    pub(crate) fn data(&self) -> Signal {
+        self.data
+    }
+
+    pub(crate) fn addr(&self) -> Signal {
+        self.addr
+    }
+}
+
+impl Synth for Rom {
+    fn synth(&self) -> String {
+        let mut s = String::from("always_comb begin\n");
+        s.push_str(&format!("case ({})\n", self.addr.name()));
+        for (addr, word) in self.words.iter().enumerate() {
+            let value = Const::new(word.clone(), self.data.width()).radix(Radix::Hex);
+            s.push_str(&format!("{}: {} = {};\n", addr, self.data.name(), value.repr()));
+        }
+        // addresses past the table read as zero
+        if (self.words.len() as u64) < 1u64.checked_shl(self.addr.width()).unwrap_or(u64::MAX) {
+            s.push_str(&format!("default: {} = '0;\n", self.data.name()));
+        }
+        s.push_str("endcase\nend\n");
+        s
+    }
+}
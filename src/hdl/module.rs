@@ -1,220 +1,1218 @@
 use std::ops::{AddAssign, SubAssign};
-use super::{Synth, Signal};
-use super::expr::{Assign};
-use super::condition::{Conditional, Conditional::*};
-//use std::ptr::{read, write};
-//use duplicate::duplicate;
+use super::{Synth, Operand, Const, Param, Radix, Signal};
+use super::expr::{Assign, Op};
+use super::condition::{Conditional, Conditional::*, Edge, Reset};
+use super::instance::{Instance};
+use super::bundle::{Bundle, InterfaceDecl};
+use super::memory::{MemInit, Rom};
+use super::case::Case;
+use super::error::{Errors, KungError, OrPanic};
+use num::BigUint;
+use std::cell::RefCell;
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-pub struct Scope<'module> {
-    cond: Conditional<'module>,
+/// Lightweight handle stored in signals to refer to the module that created them.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct ModuleId(usize);
 
-    assigns: BTreeMap<String, Assign<'module>>,
+static NEXT_MODULE_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // lets signals reach the module that created them through their id.
+    // Modules are `Rc`s and never leave their thread, a signal used on another
+    // thread doesn't find its module.
+    static MODULES: RefCell<BTreeMap<ModuleId, Weak<RefCell<Module>>>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+/// What drives a signal of a module.
+enum Driver {
+    /// Continuous assign to the whole signal, or to the word or element
+    /// rendered as the target.
+    Assign(Option<String>),
+    /// Always block, instance output or ROM.
+    Block,
+    /// `initial` block loading a memory.
+    Init,
+}
+
+/// Assignment or nested block of a scope, kept in the order they were added.
+enum Statement {
+    Assign(Assign),
+    Block(Scope),
+    Case(Case),
+}
+
+pub struct Scope {
+    cond: Conditional,
+
+    statements: Vec<Statement>,
 
     sync: bool,
-    scopes: Vec<Scope<'module>>,
+    reset: Option<Reset>,
+    // assigned when the reset is active instead of `statements`
+    resets: BTreeMap<String, Assign>,
+    errors: Errors,
 }
 
-pub struct Module<'module> {
+pub struct Module {
+    id: ModuleId,
     name: String,
-    inputs: BTreeMap<String, Signal<'module>>,
-    outputs: BTreeMap<String, Signal<'module>>,
+    params: Vec<Param>,
+    inputs: BTreeMap<String, Signal>,
+    outputs: BTreeMap<String, Signal>,
+    signals: BTreeMap<String, Signal>,
+    reset_values: BTreeMap<String, Const>,
+    interfaces: Vec<InterfaceDecl>,
 
-    assigns: BTreeMap<String, Assign<'module>>,
-    scopes: Vec<Scope<'module>>,
+    assigns: BTreeMap<String, Assign>,
+    instances: Vec<Instance>,
+    inits: Vec<MemInit>,
+    roms: Vec<Rom>,
+    scopes: Vec<Scope>,
 }
 
 pub trait SignalHolder {
-    fn logic(&self, name: &'_ str, width: u32) -> Signal<'_>;
+    fn logic(&self, name: &str, width: u32) -> Signal;
+
+    /// Same as `logic`, fails if the name is taken or too long, or if the
+    /// width is 0.
+    fn try_logic(&self, name: &str, width: u32) -> Result<Signal, KungError>;
+
+    /// Signed signal, declared as `logic signed`.
+    fn logic_signed(&self, name: &str, width: u32) -> Signal;
+
+    /// Same as `logic_signed`, fails if the name is taken or too long.
+    fn try_logic_signed(&self, name: &str, width: u32) -> Result<Signal, KungError>;
+
+    /// Signal whose width is the value of a parameter.
+    fn logic_param(&self, name: &str, width: Param) -> Signal;
+
+    /// Same as `logic_param`, fails if the name is taken or too long.
+    fn try_logic_param(&self, name: &str, width: Param) -> Result<Signal, KungError>;
+
+    /// Register reset to `reset` by the scopes created with `Module::on_reset`.
+    fn reg<T: Into<BigUint>>(&self, name: &str, width: u32, reset: T) -> Signal;
+
+    /// Same as `reg`, also fails if the reset value doesn't fit.
+    fn try_reg<T: Into<BigUint>>(&self, name: &str, width: u32, reset: T) -> Result<Signal, KungError>;
+
+    /// Memory of `depth` words, `logic [width-1:0] name [0:depth-1]`.
+    fn memory(&self, name: &str, width: u32, depth: u32) -> Signal;
+
+    /// Same as `memory`, fails if the name is taken or too long.
+    fn try_memory(&self, name: &str, width: u32, depth: u32) -> Result<Signal, KungError>;
+
+    /// Packed array with the dimensions `dims`, outermost first, of
+    /// `width`-bit elements: `logic [3:0][7:0] name`.
+    fn array(&self, name: &str, width: u32, dims: &[u32]) -> Signal;
+
+    /// Same as `array`, fails if the name is taken or too long.
+    fn try_array(&self, name: &str, width: u32, dims: &[u32]) -> Result<Signal, KungError>;
+
+    /// Bundle of signals named `prefix_field`.
+    fn bundle<B: Bundle>(&self, prefix: &str) -> B;
+
+    /// Same as `bundle`, fails if the name of a field is taken or too long.
+    fn try_bundle<B: Bundle>(&self, prefix: &str) -> Result<B, KungError>;
+
+    /// Bundle declared as an instance `name` of its SystemVerilog interface,
+    /// the fields are referred to as `name.field`.
+    fn interface<B: Bundle>(&self, name: &str) -> B;
+
+    /// Same as `interface`, fails if the interface name is taken or a field
+    /// name gets too long.
+    fn try_interface<B: Bundle>(&self, name: &str) -> Result<B, KungError>;
 }
-pub type VModule<'a> = Rc<Module<'a>>;
+pub type VModule = Rc<RefCell<Module>>;
 
-impl<'module> Synth for Module<'module> {
+impl Synth for Module {
     fn synth(&self) -> String {
         let mut s = String::new();
 
+        let mut ports: Vec<String> = self.ports().iter()
+            .filter(|(_, sig)| self.interface_of(sig.name()).is_none())
+            .map(|(dir, sig)| format!("{} {}", dir.keyword(), sig.def()))
+            .collect();
+        for iface in &self.interfaces {
+            if let Some(modport) = iface.modport {
+                ports.push(format!("{}.{} {}", iface.kind, modport, iface.name));
+            }
+        }
+
+        let params: Vec<String> = self.params.iter()
+            .filter(|param| !param.is_local())
+            .map(|param| param.def())
+            .collect();
+
         s.push_str("module ");
         s.push_str(&self.name);
-        s.push_str("();\n");
+        if !params.is_empty() {
+            s.push_str(&format!(" #({}) ", params.join(", ")));
+        }
+        s.push('(');
+        s.push_str(&ports.join(", "));
+        s.push_str(");\n");
 
-        for (_, item) in &self.inputs {
-            s.push_str("input ");
-            s.push_str(&item.def());
+        for param in self.params.iter().filter(|param| param.is_local()) {
+            s.push_str(&param.def());
             s.push_str(";\n");
         }
 
-        for (_, item) in &self.outputs {
-            s.push_str("output ");
-            s.push_str(&item.def());
+        for iface in self.interfaces.iter().filter(|iface| iface.modport.is_none()) {
+            s.push_str(&format!("{} {}();\n", iface.kind, iface.name));
+        }
+
+        for net in self.nets() {
+            s.push_str(&net.def());
             s.push_str(";\n");
         }
 
-        for (_, assign) in &self.assigns {
+        for assign in self.assigns.values() {
             s.push_str("assign ");
             s.push_str(&assign.synth(false));
-            s.push_str("\n");
+            s.push('\n');
+        }
+        for instance in &self.instances {
+            s.push_str(&instance.synth());
+        }
+        for init in &self.inits {
+            s.push_str(&init.synth());
+        }
+        for rom in &self.roms {
+            s.push('\n');
+            s.push_str(&rom.synth());
+            s.push('\n');
         }
         for scope in &self.scopes {
-            s.push_str("\n");
+            s.push('\n');
             s.push_str(&scope.synth());
-            s.push_str("\n");
+            s.push('\n');
         }
         s.push_str("endmodule\n");
         s
     }
 }
 
-impl<'module> Module<'module> {
+impl Direction {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Direction::Input => "input",
+            Direction::Output => "output",
+        }
+    }
+
+    pub fn flip(self) -> Self {
+        match self {
+            Direction::Input => Direction::Output,
+            Direction::Output => Direction::Input,
+        }
+    }
+}
+
+impl Module {
     pub fn new(name: &str) -> VModule {
-        return Rc::new(Module {
+        let module = Rc::new(RefCell::new(Module {
+            id: ModuleId(NEXT_MODULE_ID.fetch_add(1, Ordering::Relaxed)),
             name: String::from(name),
+            params: vec![],
             inputs: BTreeMap::new(),
             outputs: BTreeMap::new(),
+            signals: BTreeMap::new(),
+            reset_values: BTreeMap::new(),
+            interfaces: vec![],
+            instances: vec![],
+            inits: vec![],
+            roms: vec![],
             scopes: vec![],
 
             assigns: BTreeMap::new(),
-        })
+        }));
+
+        let id = module.borrow().id;
+        MODULES.with(|modules| modules.borrow_mut().insert(id, Rc::downgrade(&module)));
+        module
     }
 
-    pub fn comb<T>(&mut self, add_rules: T) where T:Fn(&mut Scope) -> () {
-        let mut scope = Scope::new();
-        add_rules(&mut scope);
-        self.scopes.push(scope);
+    pub fn id(&self) -> ModuleId {
+        self.id
     }
 
-    pub fn on<T>(&mut self, signal: &'module Signal<'module>, add_rules: T) where T:Fn(&mut Scope) -> () {
-        let mut scope = Scope::new();
-        scope.cond = Posedge(signal);
-        scope.sync = true;
+    /// Module with the id `id` if it is still alive. Only modules created by
+    /// the current thread can be found.
+    pub fn find(id: ModuleId) -> Option<VModule> {
+        MODULES.with(|modules| modules.borrow().get(&id).and_then(|module| module.upgrade()))
+    }
+
+    /// Adds `assign` as a continuous assign to the module the destination was
+    /// created by. Fails if the destination is an input or already driven.
+    ///
+    /// Signals only hold the id of their module, which is resolved through a
+    /// registry local to the thread that called `Module::new`. Signals can be
+    /// sent to other threads but their modules can't be found there, and
+    /// recording fails with `KungError::UnknownSignal`.
+    pub fn record(assign: Assign) -> Result<(), KungError> {
+        Module::owner(&assign.dest)?.borrow_mut().try_assign(assign)
+    }
+
+    /// Adds `assign` as a register assigned on the rising edge of `clk` to the
+    /// module the destination was created by. Registers with the same clock
+    /// and reset share one `always_ff` block. The module is found like in
+    /// `record`, on the current thread only.
+    pub fn record_seq(clk: &Signal, reset: Option<Reset>, assign: Assign) -> Result<(), KungError> {
+        let module = Module::owner(&assign.dest)?;
+        let mut module = module.borrow_mut();
+        module.check_driver(&assign.dest, &Driver::Block)?;
+
+        let same_reset = |other: &Option<Reset>| match (other, &reset) {
+            (Some(a), Some(b)) => a.active() == b.active() && a.event().is_some() == b.event().is_some(),
+            (None, None) => true,
+            _ => false,
+        };
+        let pos = module.scopes.iter().position(|scope| match &scope.cond {
+            Posedge(sig) => sig.name() == clk.name() && same_reset(&scope.reset),
+            _ => false,
+        });
+        let pos = pos.unwrap_or_else(|| {
+            module.scopes.push(Scope::clocked(Posedge(*clk), reset));
+            module.scopes.len() - 1
+        });
+
+        let mut scope = std::mem::take(&mut module.scopes[pos]);
+        let result = scope.try_add(assign);
+        module.add_resets(&mut scope);
+        module.scopes[pos] = scope;
+        result
+    }
+
+    // module of the signal, if it isn't borrowed already
+    fn owner(dest: &Signal) -> Result<VModule, KungError> {
+        let module = dest.module.and_then(Module::find)
+            .ok_or_else(|| KungError::UnknownSignal(String::from(dest.name())))?;
+
+        if module.try_borrow_mut().is_err() {
+            return Err(KungError::ModuleInUse(String::from(dest.name())));
+        }
+        Ok(module)
+    }
+
+    // fails if `dest` is an input or is already driven by something that
+    // can't share it with `driver`
+    fn check_driver(&self, dest: &Signal, driver: &Driver) -> Result<(), KungError> {
+        let name = dest.name();
+        if self.inputs.contains_key(name) {
+            return Err(KungError::InvalidDirection {
+                signal: String::from(name),
+                module: self.name.clone(),
+                instance: None,
+            });
+        }
+
+        if self.drivers().iter().any(|(sig, other)| sig.name() == name && !driver.can_share(other)) {
+            let signal = match driver {
+                Driver::Assign(Some(target)) => target.clone(),
+                _ => String::from(name),
+            };
+            return Err(KungError::DuplicateDriver { signal, module: self.name.clone() });
+        }
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = String::from(name);
+    }
+
+    fn add_param(&mut self, param: Param) -> Result<Param, KungError> {
+        if self.params.iter().any(|p| p.name() == param.name()) {
+            return Err(self.duplicate("parameter", param.name()));
+        }
+        self.params.push(param);
+        Ok(param)
+    }
+
+    /// Declares `parameter name = value`, it can be overridden by instances.
+    pub fn param(&mut self, name: &str, value: u64) -> Param {
+        self.try_param(name, value).or_panic()
+    }
+
+    /// Same as `param`, fails if the name is taken or too long.
+    pub fn try_param(&mut self, name: &str, value: u64) -> Result<Param, KungError> {
+        self.add_param(Param::try_new(name, value)?)
+    }
+
+    /// Declares `localparam name = value`.
+    pub fn localparam(&mut self, name: &str, value: u64) -> Param {
+        self.try_localparam(name, value).or_panic()
+    }
+
+    /// Same as `localparam`, fails if the name is taken or too long.
+    pub fn try_localparam(&mut self, name: &str, value: u64) -> Result<Param, KungError> {
+        self.add_param(Param::try_local(name, value)?)
+    }
+
+    fn duplicate(&self, kind: &'static str, name: &str) -> KungError {
+        KungError::Duplicate { kind, name: String::from(name), module: self.name.clone() }
+    }
+
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    /// Ports in the order of the module header, inputs first.
+    pub fn ports(&self) -> Vec<(Direction, Signal)> {
+        let inputs = self.inputs.values().map(|sig| (Direction::Input, *sig));
+        let outputs = self.outputs.values().map(|sig| (Direction::Output, *sig));
+        inputs.chain(outputs).collect()
+    }
+
+    /// Adds the fields of the bundle as ports, outputs of the bundle become
+    /// outputs of the module. Interface bundles are declared with their
+    /// modport in the header instead of field by field.
+    pub fn port_bundle<B: Bundle>(&mut self, bundle: &B) {
+        self.try_port_bundle(bundle).or_panic()
+    }
+
+    /// Same as `port_bundle`, fails without adding any port if a field is
+    /// already a port.
+    pub fn try_port_bundle<B: Bundle>(&mut self, bundle: &B) -> Result<(), KungError> {
+        let fields = bundle.fields();
+        for (_, _, sig) in &fields {
+            self.check_port(sig.name())?;
+        }
+        if let Some(name) = fields.first().and_then(|(_, _, sig)| sig.interface()) {
+            if let Some(iface) = self.interfaces.iter_mut().find(|iface| iface.name == name) {
+                iface.modport = Some(bundle.modport());
+            }
+        }
+
+        for (_, dir, sig) in fields {
+            match dir {
+                Direction::Input => self.try_add_input(sig)?,
+                Direction::Output => self.try_add_output(sig)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Interface instance or port with the name `name`.
+    pub(crate) fn interface(&self, name: &str) -> Option<&InterfaceDecl> {
+        self.interfaces.iter().find(|iface| iface.name == name)
+    }
+
+    pub(crate) fn interfaces(&self) -> &[InterfaceDecl] {
+        &self.interfaces
+    }
+
+    /// Interface the signal is a field of.
+    pub(crate) fn interface_of(&self, signal: &str) -> Option<&InterfaceDecl> {
+        self.interfaces.iter().find(|iface| iface.fields.iter().any(|sig| sig.name() == signal))
+    }
+
+    /// Declarations of the interfaces used by the module.
+    pub fn interface_defs(&self) -> Vec<(&str, &str)> {
+        self.interfaces.iter().map(|iface| (iface.kind, iface.definition.as_str())).collect()
+    }
+
+    fn is_port(&self, name: &str) -> bool {
+        self.inputs.contains_key(name) || self.outputs.contains_key(name)
+    }
+
+    fn driven(&self) -> BTreeMap<String, Signal> {
+        self.drivers().into_iter().map(|(sig, _)| (String::from(sig.name()), sig)).collect()
+    }
+
+    // every signal driven in the module, with what drives it
+    fn drivers(&self) -> Vec<(Signal, Driver)> {
+        let mut drivers: Vec<(Signal, Driver)> = self.assigns.values()
+            .map(|assign| (assign.dest, Driver::of(assign)))
+            .collect();
+        for instance in &self.instances {
+            drivers.extend(instance.outputs().into_iter().map(|sig| (sig, Driver::Block)));
+        }
+        for init in &self.inits {
+            drivers.push((init.mem(), Driver::Init));
+        }
+        for rom in &self.roms {
+            drivers.push((rom.data(), Driver::Block));
+        }
+        for scope in &self.scopes {
+            let mut dests = BTreeMap::new();
+            scope.collect_dests(&mut dests);
+            drivers.extend(dests.into_values().map(|sig| (sig, Driver::Block)));
+        }
+        drivers
+    }
+
+    fn read(&self) -> BTreeMap<String, Signal> {
+        let mut reads = vec![];
+        for assign in self.assigns.values() {
+            assign.op.collect_signals(&mut reads);
+            if let Some(index) = &assign.index {
+                index.collect_signals(&mut reads);
+            }
+        }
+        for instance in &self.instances {
+            instance.collect_reads(&mut reads);
+        }
+        for rom in &self.roms {
+            reads.push(rom.addr());
+        }
+        for scope in &self.scopes {
+            scope.collect_reads(&mut reads);
+        }
+        reads.into_iter().map(|sig| (String::from(sig.name()), sig)).collect()
+    }
+
+    /// Signals that are not ports: everything created with
+    /// `SignalHolder::logic` and everything driven inside the module.
+    pub fn nets(&self) -> Vec<Signal> {
+        // the copies used by the logic are declared rather than the ones
+        // created, they keep what was added later, like `signed()`
+        let mut nets = self.signals.clone();
+        for (name, sig) in self.read() {
+            if let Some(net) = nets.get_mut(&name) {
+                *net = sig;
+            }
+        }
+        nets.extend(self.driven());
+
+        nets.into_iter()
+            .filter(|(name, _)| !self.is_port(name) && self.interface_of(name).is_none())
+            .map(|(_, sig)| sig)
+            .collect()
+    }
+
+    /// Describes the signals that are never driven or never read.
+    pub fn warnings(&self) -> Vec<String> {
+        let driven = self.driven();
+        let read = self.read();
+        let mut warnings = vec![];
+
+        for sig in self.nets().iter().chain(self.outputs.values()) {
+            if !driven.contains_key(sig.name()) {
+                warnings.push(format!("'{}' is never driven in module '{}'", sig.name(), self.name));
+            }
+        }
+        for sig in self.nets().iter().chain(self.inputs.values()) {
+            if !read.contains_key(sig.name()) {
+                warnings.push(format!("'{}' is never read in module '{}'", sig.name(), self.name));
+            }
+        }
+        warnings
+    }
+
+    /// Instantiates `module` as `name`, `connections` bind the ports of the
+    /// child to expressions of this module.
+    pub fn instance<S, I>(&mut self, module: &VModule, name: &str, connections: I)
+        where S: AsRef<str>, I: IntoIterator<Item = (S, Op)>
+    {
+        self.try_instance(module, name, connections).or_panic()
+    }
+
+    /// Same as `instance`, fails if the connections don't match the ports.
+    pub fn try_instance<S, I>(&mut self, module: &VModule, name: &str, connections: I) -> Result<(), KungError>
+        where S: AsRef<str>, I: IntoIterator<Item = (S, Op)>
+    {
+        self.try_instance_with_params(module, name, &[], connections)
+    }
+
+    /// Same as `instance`, `params` override parameters of the child.
+    pub fn instance_with_params<S, I>(&mut self, module: &VModule, name: &str, params: &[(&str, u64)], connections: I)
+        where S: AsRef<str>, I: IntoIterator<Item = (S, Op)>
+    {
+        self.try_instance_with_params(module, name, params, connections).or_panic()
+    }
+
+    /// Same as `instance_with_params`, fails if the connections don't match
+    /// the ports or a parameter can't be overridden.
+    pub fn try_instance_with_params<S, I>(&mut self, module: &VModule, name: &str, params: &[(&str, u64)], connections: I)
+        -> Result<(), KungError>
+        where S: AsRef<str>, I: IntoIterator<Item = (S, Op)>
+    {
+        // the module being built is already borrowed mutably
+        match module.try_borrow() {
+            Ok(child) if child.id != self.id => {},
+            _ => return Err(KungError::SelfInstance(self.name.clone())),
+        }
+        if self.instances.iter().any(|inst| inst.name() == name) {
+            return Err(self.duplicate("instance", name));
+        }
+
+        let instance = Instance::try_new(module, name, params, connections)?;
+        for sig in instance.outputs() {
+            if self.inputs.contains_key(sig.name()) {
+                return Err(KungError::InvalidDirection {
+                    signal: String::from(sig.name()),
+                    module: self.name.clone(),
+                    instance: Some(String::from(name)),
+                });
+            }
+            self.check_driver(&sig, &Driver::Block)?;
+        }
+        self.instances.push(instance);
+        Ok(())
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    fn add_init(&mut self, init: MemInit) -> Result<(), KungError> {
+        self.check_driver(&init.mem(), &Driver::Init)?;
+        self.inits.push(init);
+        Ok(())
+    }
+
+    /// Loads `contents` into `mem` with `$readmemh` (`Radix::Hex`) or
+    /// `$readmemb` (`Radix::Bin`) from the file `path`, which is written
+    /// along with the design.
+    pub fn readmem<I, T>(&mut self, mem: &Signal, path: &str, radix: Radix, contents: I)
+        where I: IntoIterator<Item = T>, T: Into<BigUint>
+    {
+        self.try_readmem(mem, path, radix, contents).or_panic()
+    }
+
+    /// Same as `readmem`, fails if `mem` isn't a memory or is already
+    /// initialized, or if the contents don't fit.
+    pub fn try_readmem<I, T>(&mut self, mem: &Signal, path: &str, radix: Radix, contents: I) -> Result<(), KungError>
+        where I: IntoIterator<Item = T>, T: Into<BigUint>
+    {
+        self.add_init(MemInit::try_new(mem, Some((path, radix)), contents)?)
+    }
+
+    /// Assigns `contents` to the first words of `mem` in an `initial` block.
+    pub fn init<I, T>(&mut self, mem: &Signal, contents: I)
+        where I: IntoIterator<Item = T>, T: Into<BigUint>
+    {
+        self.try_init(mem, contents).or_panic()
+    }
+
+    /// Same as `init`, fails like `try_readmem`.
+    pub fn try_init<I, T>(&mut self, mem: &Signal, contents: I) -> Result<(), KungError>
+        where I: IntoIterator<Item = T>, T: Into<BigUint>
+    {
+        self.add_init(MemInit::try_new(mem, None, contents)?)
+    }
+
+    /// Lookup table driving `data` with the word at `addr`, rendered as a
+    /// `case` in an `always_comb` block. Missing words read as zero.
+    pub fn rom<I, T>(&mut self, data: &Signal, addr: &Signal, contents: I)
+        where I: IntoIterator<Item = T>, T: Into<BigUint>
+    {
+        self.try_rom(data, addr, contents).or_panic()
+    }
+
+    /// Same as `rom`, fails if `data` is already driven or the contents
+    /// don't fit.
+    pub fn try_rom<I, T>(&mut self, data: &Signal, addr: &Signal, contents: I) -> Result<(), KungError>
+        where I: IntoIterator<Item = T>, T: Into<BigUint>
+    {
+        let rom = Rom::try_new(data, addr, contents)?;
+        self.check_driver(&rom.data(), &Driver::Block)?;
+        self.roms.push(rom);
+        Ok(())
+    }
+
+    /// Paths and contents of the files read by `$readmemh`/`$readmemb`.
+    pub fn mem_files(&self) -> Vec<(String, String)> {
+        self.inits.iter().filter_map(|init| init.file()).collect()
+    }
+
+    /// Combinational read port of a memory, `assign data = mem[addr];`.
+    pub fn read_port(&mut self, data: &Signal, mem: &Signal, addr: Op) {
+        self.try_read_port(data, mem, addr).or_panic()
+    }
+
+    /// Same as `read_port`, fails like `try_assign`.
+    pub fn try_read_port(&mut self, data: &Signal, mem: &Signal, addr: Op) -> Result<(), KungError> {
+        self.try_assign(Assign::try_new(data, mem.index(addr))?)
+    }
+
+    /// Registered read port of a memory, `data <= mem[addr];` on the rising
+    /// edge of `clk`.
+    pub fn read_port_on(&mut self, clk: &Signal, data: &Signal, mem: &Signal, addr: Op) {
+        let mut scope = Scope::clocked(Posedge(*clk), None);
+        scope.assign(data, mem.index(addr));
+        self.push_scope(scope).or_panic()
+    }
+
+    /// Same as `read_port_on`, returns the problems of the block like
+    /// `try_comb`.
+    pub fn try_read_port_on(&mut self, clk: &Signal, data: &Signal, mem: &Signal, addr: Op) -> Result<(), Vec<KungError>> {
+        let mut scope = Scope::clocked(Posedge(*clk), None).collecting();
+        scope.assign(data, mem.index(addr));
+        self.push_scope(scope)
+    }
+
+    pub fn comb<T>(&mut self, add_rules: T) where T:Fn(&mut Scope) {
+        self.add_scope(Scope::new(), add_rules).or_panic()
+    }
+
+    /// Same as `comb`, returns every width mismatch and duplicate assign of
+    /// the block and its branches instead of panicking on the first one.
+    /// Nothing is added to the module if there are any.
+    pub fn try_comb<T>(&mut self, add_rules: T) -> Result<(), Vec<KungError>> where T:Fn(&mut Scope) {
+        self.add_scope(Scope::new().collecting(), add_rules)
+    }
+
+    pub fn on<T>(&mut self, signal: &Signal, add_rules: T) where T:Fn(&mut Scope) {
+        self.add_scope(Scope::clocked(Posedge(*signal), None), add_rules).or_panic()
+    }
+
+    /// Same as `on`, returns the problems of the block like `try_comb`.
+    pub fn try_on<T>(&mut self, signal: &Signal, add_rules: T) -> Result<(), Vec<KungError>> where T:Fn(&mut Scope) {
+        self.add_scope(Scope::clocked(Posedge(*signal), None).collecting(), add_rules)
+    }
+
+    /// Same as `on` on the falling edge, `always_ff @(negedge clk)`.
+    pub fn on_negedge<T>(&mut self, signal: &Signal, add_rules: T) where T:Fn(&mut Scope) {
+        self.add_scope(Scope::clocked(Negedge(*signal), None), add_rules).or_panic()
+    }
+
+    /// Same as `on_negedge`, returns the problems of the block like `try_comb`.
+    pub fn try_on_negedge<T>(&mut self, signal: &Signal, add_rules: T) -> Result<(), Vec<KungError>>
+        where T:Fn(&mut Scope)
+    {
+        self.add_scope(Scope::clocked(Negedge(*signal), None).collecting(), add_rules)
+    }
+
+    /// Block sensitive to several events, `always_ff @(posedge clk or posedge rst)`.
+    pub fn on_events<T>(&mut self, events: &[(Edge, Signal)], add_rules: T) where T:Fn(&mut Scope) {
+        let scope = self.events_scope(events).or_panic();
+        self.add_scope(scope, add_rules).or_panic()
+    }
+
+    /// Same as `on_events`, returns the problems of the block like `try_comb`.
+    pub fn try_on_events<T>(&mut self, events: &[(Edge, Signal)], add_rules: T) -> Result<(), Vec<KungError>>
+        where T:Fn(&mut Scope)
+    {
+        let scope = self.events_scope(events).map_err(|err| vec![err])?;
+        self.add_scope(scope.collecting(), add_rules)
+    }
+
+    // block sensitive to `events`, there has to be at least one
+    fn events_scope(&self, events: &[(Edge, Signal)]) -> Result<Scope, KungError> {
+        if events.is_empty() {
+            return Err(KungError::NoEvents(self.name.clone()));
+        }
+        Ok(Scope::clocked(Events(events.to_vec()), None))
+    }
+
+    /// Same as `on` with `reset` putting every register assigned in the scope
+    /// to its reset value, zero for registers created without one.
+    pub fn on_reset<T>(&mut self, signal: &Signal, reset: Reset, add_rules: T) where T:Fn(&mut Scope) {
+        self.add_scope(Scope::clocked(Posedge(*signal), Some(reset)), add_rules).or_panic()
+    }
+
+    /// Same as `on_reset`, returns the problems of the block like `try_comb`.
+    pub fn try_on_reset<T>(&mut self, signal: &Signal, reset: Reset, add_rules: T) -> Result<(), Vec<KungError>>
+        where T:Fn(&mut Scope)
+    {
+        self.add_scope(Scope::clocked(Posedge(*signal), Some(reset)).collecting(), add_rules)
+    }
+
+    /// Intentional latch, `always_latch`, assigned with non-blocking assignments.
+    pub fn latch<T>(&mut self, add_rules: T) where T:Fn(&mut Scope) {
+        self.add_scope(Scope::clocked(Latch, None), add_rules).or_panic()
+    }
+
+    /// Same as `latch`, returns the problems of the block like `try_comb`.
+    pub fn try_latch<T>(&mut self, add_rules: T) -> Result<(), Vec<KungError>> where T:Fn(&mut Scope) {
+        self.add_scope(Scope::clocked(Latch, None).collecting(), add_rules)
+    }
+
+    fn add_scope<T>(&mut self, mut scope: Scope, add_rules: T) -> Result<(), Vec<KungError>> where T:Fn(&mut Scope) {
         add_rules(&mut scope);
+        self.push_scope(scope)
+    }
+
+    // adds the scope if neither its rules nor the signals it drives have
+    // problems
+    fn push_scope(&mut self, mut scope: Scope) -> Result<(), Vec<KungError>> {
+        let mut errors = vec![];
+        scope.take_errors(&mut errors);
+        let mut dests = BTreeMap::new();
+        scope.collect_dests(&mut dests);
+        for dest in dests.values() {
+            if let Err(err) = self.check_driver(dest, &Driver::Block) {
+                errors.push(err);
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        self.add_resets(&mut scope);
         self.scopes.push(scope);
+        Ok(())
     }
-}
 
-impl<'module> SignalHolder for VModule<'module> {
-    fn logic(&self, name: &'_ str, width: u32) -> Signal<'_> {
-        let mut sig = Signal::new(name, width);
-        sig.module = Some(Rc::clone(self));
-        sig
+    // resets the registers of the scope to their values if it has a reset
+    fn add_resets(&self, scope: &mut Scope) {
+        if scope.reset.is_none() {
+            return;
+        }
+
+        let mut dests = BTreeMap::new();
+        scope.collect_dests(&mut dests);
+        for (name, dest) in dests {
+            // memories keep their contents
+            if dest.words().is_some() || scope.resets.contains_key(&name) {
+                continue;
+            }
+            let value = self.reset_values.get(&name).cloned()
+                .unwrap_or_else(|| Const::new(0u32, dest.width()));
+            scope.resets.insert(name, Assign::new(&dest, value.into()));
+        }
     }
 }
 
-impl<'module> AddAssign<Assign<'module>> for Module<'module> {
-    fn add_assign(&mut self, other: Assign<'module>) {
-        if let None = self.assigns.get(other.dest.name()) {
-            self.assigns.insert(String::from(other.dest.name()), other);
-        } else {
-            panic!("assign with destination '{}' already defined in this module", other.dest.name());
+impl Module {
+    // a port can only be declared once, in one direction
+    fn check_port(&self, name: &str) -> Result<(), KungError> {
+        if self.inputs.contains_key(name) {
+            return Err(self.duplicate("input", name));
+        }
+        if self.outputs.contains_key(name) {
+            return Err(self.duplicate("output", name));
         }
+        Ok(())
     }
-}
 
-impl<'module> AddAssign<Signal<'module>> for VModule<'module> {
-    fn add_assign(&mut self, other: Signal<'module>) {
-        let val = Rc::get_mut(self).unwrap();
-        if !val.inputs.contains_key(other.name()) {
-            val.inputs.insert(String::from(other.name()), other.copy());
-        } else {
-            panic!("input with name '{}' already defined in the module", other.name());
+    /// Adds `sig` as an input, like `m += sig`.
+    pub fn try_add_input(&mut self, sig: Signal) -> Result<(), KungError> {
+        self.check_port(sig.name())?;
+        self.inputs.insert(String::from(sig.name()), sig.copy());
+        Ok(())
+    }
+
+    /// Adds `sig` as an output, like `m -= sig`.
+    pub fn try_add_output(&mut self, sig: Signal) -> Result<(), KungError> {
+        self.check_port(sig.name())?;
+        self.outputs.insert(String::from(sig.name()), sig);
+        Ok(())
+    }
+
+    /// Adds a continuous assign, like `m += assign`. Fails if the destination
+    /// is an input or is already driven.
+    pub fn try_assign(&mut self, assign: Assign) -> Result<(), KungError> {
+        self.check_driver(&assign.dest, &Driver::of(&assign))?;
+        self.assigns.insert(assign.target(), assign);
+        Ok(())
+    }
+
+    fn add_signal(&mut self, mut sig: Signal) -> Result<Signal, KungError> {
+        if self.signals.contains_key(sig.name()) {
+            return Err(self.duplicate("signal", sig.name()));
+        }
+
+        sig.module = Some(self.id);
+        self.signals.insert(String::from(sig.name()), sig);
+        Ok(sig)
+    }
+
+    fn add_interface<B: Bundle>(&mut self, name: &str) -> Result<B, KungError> {
+        if self.interface(name).is_some() {
+            return Err(self.duplicate("interface", name));
         }
+
+        let fields = B::layout().into_iter()
+            .map(|(field, _, width)| {
+                let mut sig = Signal::interface_field(name, field, width)?;
+                sig.module = Some(self.id);
+                Ok(sig)
+            })
+            .collect::<Result<Vec<Signal>, KungError>>()?;
+
+        self.interfaces.push(InterfaceDecl {
+            name: String::from(name),
+            kind: B::name(),
+            definition: B::interface(),
+            modport: None,
+            fields: fields.clone(),
+        });
+        Ok(B::from_signals(fields, false))
+    }
+}
+
+impl SignalHolder for VModule {
+    fn logic(&self, name: &str, width: u32) -> Signal {
+        self.try_logic(name, width).or_panic()
+    }
+
+    fn try_logic(&self, name: &str, width: u32) -> Result<Signal, KungError> {
+        self.borrow_mut().add_signal(Signal::try_new(name, width)?)
+    }
+
+    fn logic_signed(&self, name: &str, width: u32) -> Signal {
+        self.try_logic_signed(name, width).or_panic()
+    }
+
+    fn try_logic_signed(&self, name: &str, width: u32) -> Result<Signal, KungError> {
+        self.borrow_mut().add_signal(Signal::try_new(name, width)?.signed())
+    }
+
+    fn logic_param(&self, name: &str, width: Param) -> Signal {
+        self.try_logic_param(name, width).or_panic()
+    }
+
+    fn try_logic_param(&self, name: &str, width: Param) -> Result<Signal, KungError> {
+        self.borrow_mut().add_signal(Signal::try_new_param(name, width)?)
+    }
+
+    fn reg<T: Into<BigUint>>(&self, name: &str, width: u32, reset: T) -> Signal {
+        self.try_reg(name, width, reset).or_panic()
+    }
+
+    fn try_reg<T: Into<BigUint>>(&self, name: &str, width: u32, reset: T) -> Result<Signal, KungError> {
+        let reset = Const::try_new(reset, width)?;
+        let mut module = self.borrow_mut();
+        let sig = module.add_signal(Signal::try_new(name, width)?)?;
+        module.reset_values.insert(String::from(name), reset);
+        Ok(sig)
+    }
+
+    fn memory(&self, name: &str, width: u32, depth: u32) -> Signal {
+        self.try_memory(name, width, depth).or_panic()
+    }
+
+    fn try_memory(&self, name: &str, width: u32, depth: u32) -> Result<Signal, KungError> {
+        self.borrow_mut().add_signal(Signal::try_new(name, width)?.depth(depth))
+    }
+
+    fn array(&self, name: &str, width: u32, dims: &[u32]) -> Signal {
+        self.try_array(name, width, dims).or_panic()
+    }
+
+    fn try_array(&self, name: &str, width: u32, dims: &[u32]) -> Result<Signal, KungError> {
+        let sig = dims.iter().rev().fold(Signal::try_new(name, width)?, |sig, dim| sig.packed(*dim));
+        self.borrow_mut().add_signal(sig)
+    }
+
+    fn bundle<B: Bundle>(&self, prefix: &str) -> B {
+        self.try_bundle(prefix).or_panic()
+    }
+
+    fn try_bundle<B: Bundle>(&self, prefix: &str) -> Result<B, KungError> {
+        let signals = B::layout().into_iter()
+            .map(|(field, _, width)| self.try_logic(&format!("{}_{}", prefix, field), width))
+            .collect::<Result<Vec<Signal>, KungError>>()?;
+        Ok(B::from_signals(signals, false))
+    }
+
+    fn interface<B: Bundle>(&self, name: &str) -> B {
+        self.try_interface(name).or_panic()
+    }
+
+    fn try_interface<B: Bundle>(&self, name: &str) -> Result<B, KungError> {
+        self.borrow_mut().add_interface(name)
     }
 }
 
-impl<'module> SubAssign<Signal<'module>> for Module<'module> {
-    fn sub_assign(&mut self, other: Signal<'module>) {
-        if let None = self.outputs.get(other.name()) {
-            self.outputs.insert(String::from(other.name()), other);
-        } else {
-            panic!("output with name '{}' already defined in the module", other.name());
+impl Synth for VModule {
+    fn synth(&self) -> String {
+        self.borrow().synth()
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        // the registry is gone if the thread is exiting
+        let _ = MODULES.try_with(|modules| modules.borrow_mut().remove(&self.id));
+    }
+}
+
+impl AddAssign<Assign> for Module {
+    fn add_assign(&mut self, other: Assign) {
+        self.try_assign(other).or_panic()
+    }
+}
+
+impl AddAssign<Signal> for VModule {
+    fn add_assign(&mut self, other: Signal) {
+        self.borrow_mut().try_add_input(other).or_panic()
+    }
+}
+
+impl AddAssign<Assign> for VModule {
+    fn add_assign(&mut self, other: Assign) {
+        *self.borrow_mut() += other;
+    }
+}
+
+impl SubAssign<Signal> for VModule {
+    fn sub_assign(&mut self, other: Signal) {
+        *self.borrow_mut() -= other;
+    }
+}
+
+impl SubAssign<Signal> for Module {
+    fn sub_assign(&mut self, other: Signal) {
+        self.try_add_output(other).or_panic()
+    }
+}
+
+impl Driver {
+    fn of(assign: &Assign) -> Self {
+        Driver::Assign(assign.index.as_ref().map(|_| assign.target()))
+    }
+
+    // separate words or elements of a signal can each have their own
+    // continuous assign, and memories loaded by `initial` can still be
+    // written by an always block
+    fn can_share(&self, other: &Driver) -> bool {
+        match (self, other) {
+            (Driver::Assign(Some(a)), Driver::Assign(Some(b))) => a != b,
+            (Driver::Init, Driver::Block) | (Driver::Block, Driver::Init) => true,
+            _ => false,
         }
     }
 }
 
-impl<'module> Scope<'module> {
+impl Default for Scope {
+    fn default() -> Self {
+        Scope::new()
+    }
+}
+
+impl Scope {
     pub fn new() -> Self {
         Scope {
             cond: AlwaysComb,
-            scopes: vec![],
 
-            assigns: BTreeMap::new(),
+            statements: vec![],
             sync: false,
+            reset: None,
+            resets: BTreeMap::new(),
+            errors: Errors::default(),
         }
     }
 
-    pub fn when<T>(&mut self, _cond: &str, add_rules: T) -> &mut Self where T: Fn(&mut Scope) -> () {
+    // always block sensitive to `cond`, assigned with non-blocking assignments
+    fn clocked(cond: Conditional, reset: Option<Reset>) -> Self {
         let mut scope = Scope::new();
+        scope.cond = cond;
+        scope.sync = true;
+        scope.reset = reset;
+        scope
+    }
 
-        scope.sync = self.sync;
-        self.cond = When(true);
-
-        add_rules(&mut scope);
-        self.scopes.push(scope);
-        return self
+    // keeps the errors of the scope and its branches for `take_errors`
+    // instead of panicking
+    fn collecting(mut self) -> Self {
+        self.errors = Errors::collecting();
+        self
     }
 
-    pub fn elsewhen<T>(&mut self, _stub: bool, add_rules: T) -> &mut Self where T: Fn(&mut Scope) -> () {
+    /// Scope of a branch, assigned like its parent and keeping its errors
+    /// like `errors`.
+    pub(crate) fn nested(sync: bool, errors: &Errors) -> Self {
         let mut scope = Scope::new();
+        scope.sync = sync;
+        scope.errors = errors.nested();
+        scope
+    }
 
-        scope.sync = self.sync;
-        self.cond = ElseWhen(true);
+    fn block<T>(&mut self, cond: Conditional, add_rules: T) where T: Fn(&mut Scope) {
+        let mut scope = Scope::nested(self.sync, &self.errors);
+        scope.cond = cond;
 
         add_rules(&mut scope);
-        self.scopes.push(scope);
-        return self
+        self.statements.push(Statement::Block(scope));
     }
 
-    pub fn otherwise<T>(&mut self, add_rules: T) -> () where T: Fn(&mut Scope) -> () {
-        let mut scope = Scope::new();
-        scope.cond = Otherwise;
-        scope.sync = self.sync;
+    // `else` branches have to follow an `if` or an `else if`, the branch is
+    // left out if it doesn't
+    fn check_chain(&mut self, branch: &str) -> bool {
+        match self.statements.last() {
+            Some(Statement::Block(Scope { cond: When(_), .. })) |
+            Some(Statement::Block(Scope { cond: ElseWhen(_), .. })) => true,
+            _ => {
+                self.errors.push(KungError::InvalidStatement(format!("{} has to follow when or elsewhen", branch)));
+                false
+            },
+        }
+    }
 
-        add_rules(&mut scope);
-        self.scopes.push(scope);
+    pub fn when<T>(&mut self, cond: Op, add_rules: T) -> &mut Self where T: Fn(&mut Scope) {
+        self.block(When(cond), add_rules);
+        self
     }
 
-    fn statements(&self, sync: bool) -> String {
-        let mut s = String::new();
+    pub fn elsewhen<T>(&mut self, cond: Op, add_rules: T) -> &mut Self where T: Fn(&mut Scope) {
+        if self.check_chain("elsewhen") {
+            self.block(ElseWhen(cond), add_rules);
+        }
+        self
+    }
 
-        for (_, assign) in &self.assigns {
-            s.push_str(&assign.synth(sync));
-            s.push_str("\n");
+    pub fn otherwise<T>(&mut self, add_rules: T) where T: Fn(&mut Scope) {
+        if self.check_chain("otherwise") {
+            self.block(Otherwise, add_rules);
         }
+    }
 
-        for scope in self.scopes.iter() {
-            s.push_str(&scope.synth());
+    /// Starts a `case` on `expr`, the arms are added to the returned builder.
+    pub fn switch(&mut self, expr: Op) -> &mut Case {
+        self.statements.push(Statement::Case(Case::new(expr, self.sync, self.errors.nested())));
+        match self.statements.last_mut() {
+            Some(Statement::Case(case)) => case,
+            _ => unreachable!(),
+        }
+    }
+
+    fn assigned(&self, target: &str) -> Option<&Assign> {
+        self.statements.iter().find_map(|statement| match statement {
+            Statement::Assign(assign) if assign.target() == target => Some(assign),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn try_add(&mut self, assign: Assign) -> Result<(), KungError> {
+        let target = assign.target();
+        if self.assigned(&target).is_some() {
+            return Err(KungError::DuplicateAssign(target));
+        }
+        self.statements.push(Statement::Assign(assign));
+        Ok(())
+    }
+
+    // adds the assignment, or reports why it can't be added
+    fn add(&mut self, assign: Result<Assign, KungError>) {
+        if let Err(err) = assign.and_then(|assign| self.try_add(assign)) {
+            self.errors.push(err);
         }
-        return s;
+    }
+
+    /// Assigns `op` to `dest`, with `=` in combinational blocks and `<=` in
+    /// clocked ones.
+    pub fn assign(&mut self, dest: &Signal, op: Op) -> &mut Self {
+        self.add(Assign::try_new(dest, op));
+        self
+    }
+
+    /// Same as `assign`, returns the error instead of leaving it to the
+    /// builder of the block.
+    pub fn try_assign(&mut self, dest: &Signal, op: Op) -> Result<&mut Self, KungError> {
+        self.try_add(Assign::try_new(dest, op)?)?;
+        Ok(self)
+    }
+
+    /// Write port of a memory, `mem[addr] <= data;`.
+    pub fn write(&mut self, mem: &Signal, addr: Op, data: Op) -> &mut Self {
+        self.add(Assign::try_indexed(mem, addr, data));
+        self
+    }
+
+    // moves the errors of the scope and of its branches to `errors`
+    pub(crate) fn take_errors(&mut self, errors: &mut Vec<KungError>) {
+        self.errors.take(errors);
+        for statement in &mut self.statements {
+            match statement {
+                Statement::Assign(_) => {},
+                Statement::Block(scope) => scope.take_errors(errors),
+                Statement::Case(case) => case.take_errors(errors),
+            }
+        }
+    }
+
+    fn collect_dests(&self, driven: &mut BTreeMap<String, Signal>) {
+        for statement in &self.statements {
+            match statement {
+                Statement::Assign(assign) => {
+                    driven.insert(String::from(assign.dest.name()), assign.dest);
+                },
+                Statement::Block(scope) => scope.collect_dests(driven),
+                Statement::Case(case) => {
+                    for scope in case.scopes() {
+                        scope.collect_dests(driven);
+                    }
+                },
+            }
+        }
+    }
+
+    fn collect_reads(&self, reads: &mut Vec<Signal>) {
+        if let Some(reset) = &self.reset {
+            reads.push(reset.signal());
+        }
+        match &self.cond {
+            When(cond) | ElseWhen(cond) => cond.collect_signals(reads),
+            cond => reads.extend(cond.events().into_iter().map(|(_, sig)| sig)),
+        }
+        for statement in &self.statements {
+            match statement {
+                Statement::Assign(assign) => {
+                    assign.op.collect_signals(reads);
+                    if let Some(index) = &assign.index {
+                        index.collect_signals(reads);
+                    }
+                },
+                Statement::Block(scope) => scope.collect_reads(reads),
+                Statement::Case(case) => {
+                    case.collect_reads(reads);
+                    for scope in case.scopes() {
+                        scope.collect_reads(reads);
+                    }
+                },
+            }
+        }
+    }
+
+    pub(crate) fn statements(&self, sync: bool) -> String {
+        let mut s = String::new();
+
+        for statement in &self.statements {
+            match statement {
+                Statement::Assign(assign) => {
+                    s.push_str(&assign.synth(sync));
+                    s.push('\n');
+                },
+                Statement::Block(scope) => s.push_str(&scope.synth()),
+                Statement::Case(case) => s.push_str(&case.synth()),
+            }
+        }
+        s
     }
 }
 
-/*
 impl AddAssign<Assign> for Scope {
     fn add_assign(&mut self, other: Assign) {
-        if let None = self.assigns.get(other.dest.name()) {
-            self.assigns.insert(String::from(other.dest.name()), other);
-        } else {
-            panic!("assign with destination '{}' already defined in this scope", other.dest.name());
-        }
+        self.add(Ok(other));
     }
 }
-*/
 
-impl Synth for Scope<'_> {
+impl Synth for Scope {
     fn synth(&self) -> String {
         let mut s = String::new();
 
         match &self.cond {
-            Posedge(signal) => {
-                s.push_str("always_ff @(posedge ");
-                s.push_str(&signal.name());
-                s.push_str(") begin\n");
+            Posedge(_) | Negedge(_) | Events(_) => {
+                let mut events = self.cond.events();
+                events.extend(self.reset.and_then(|reset| reset.event()));
+                let events: Vec<String> = events.iter()
+                    .map(|(edge, sig)| format!("{} {}", edge.keyword(), sig.name()))
+                    .collect();
+                s.push_str(&format!("always_ff @({}) begin\n", events.join(" or ")));
+            },
+            Latch => {
+                s.push_str("always_latch begin\n");
             },
             When(cond) => {
-                s.push_str(&format!("if ({}) begin\n", cond));
+                s.push_str(&format!("if ({}) begin\n", cond.repr()));
             },
             ElseWhen(cond) => {
-                s.push_str(&format!("else if ({}) begin\n", cond));
+                s.push_str(&format!("else if ({}) begin\n", cond.repr()));
             },
             Otherwise => {
                 s.push_str("else begin\n");
@@ -223,7 +1221,19 @@ impl Synth for Scope<'_> {
                 s.push_str("always_comb begin\n");
             }
         }
-        s.push_str(&self.statements(self.sync));
+        match &self.reset {
+            Some(reset) => {
+                s.push_str(&format!("if ({}) begin\n", reset.active()));
+                for assign in self.resets.values() {
+                    s.push_str(&assign.synth(self.sync));
+                    s.push('\n');
+                }
+                s.push_str("end\nelse begin\n");
+                s.push_str(&self.statements(self.sync));
+                s.push_str("end\n");
+            },
+            None => s.push_str(&self.statements(self.sync)),
+        }
         s.push_str("end\n");
         s
     }
@@ -0,0 +1,86 @@
+use arraystring::{ArrayString, typenum::U64};
+use num::BigInt;
+use super::{Operand};
+use super::error::{KungError, OrPanic};
+
+pub(crate) type ParamName = ArrayString<U64>;
+
+/// Module parameter or localparam. It renders as its name, so widths and
+/// expressions using it stay symbolic in the generated code, while `value`
+/// is used to check widths.
+#[derive(Clone, Copy)]
+pub struct Param {
+    name: ParamName,
+    value: u64,
+    local: bool,
+}
+
+impl Param {
+    pub fn new(name: &str, value: u64) -> Self {
+        Param::try_new(name, value).or_panic()
+    }
+
+    /// Same as `new`, fails if the name is too long.
+    pub fn try_new(name: &str, value: u64) -> Result<Self, KungError> {
+        let name = ParamName::try_from_str(name)
+            .map_err(|_| KungError::NameTooLong(String::from(name)))?;
+
+        Ok(Param {
+            name,
+            value,
+            local: false,
+        })
+    }
+
+    pub fn local(name: &str, value: u64) -> Self {
+        Param::try_local(name, value).or_panic()
+    }
+
+    /// Same as `local`, fails if the name is too long.
+    pub fn try_local(name: &str, value: u64) -> Result<Self, KungError> {
+        let mut param = Param::try_new(name, value)?;
+        param.local = true;
+        Ok(param)
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
+
+    pub fn def(&self) -> String {
+        let keyword = if self.local { "localparam" } else { "parameter" };
+        format!("{} {} = {}", keyword, self.name, self.value)
+    }
+}
+
+impl Operand for Param {
+    fn repr(&self) -> String {
+        self.name.to_string()
+    }
+
+    // same as for plain integers, the bits needed to hold the value
+    fn width(&self) -> u32 {
+        (64 - self.value.leading_zeros()).max(1)
+    }
+
+    fn is_signed(&self) -> bool {
+        true
+    }
+
+    // declared without a type, so it is 32 bits wide whatever the value
+    fn is_sized(&self) -> bool {
+        false
+    }
+
+    fn const_value(&self) -> Option<BigInt> {
+        Some(BigInt::from(self.value))
+    }
+}
@@ -1,198 +1,216 @@
-//use std::ops::{Add, Sub, Shl, Shr, Mul, Div, BitAnd, BitOr, BitXor, Not};
-use std::ops::{Add};
+use std::ops::{Add, Sub, Shl, Shr, Mul, Div, BitAnd, BitOr, BitXor, Not};
 use arraystring::{ArrayString, typenum::U64};
-use super::{Operand};
+use super::{Const, Operand, Param};
+use super::param::{ParamName};
 use super::expr::{Op};
-use super::module::{VModule};
-//use super::condition::{Condition};
+use super::module::{ModuleId};
+use super::error::{KungError, OrPanic};
 use duplicate::duplicate;
-//use std::cmp::Ordering;
 
 type SignalName = ArrayString<U64>;
 
-#[derive(Clone)]
-pub struct Signal<'module> {
+/// Maximum number of packed dimensions above the element of a signal.
+const MAX_PACKED: usize = 3;
+
+#[derive(Clone, Copy)]
+pub struct Signal {
     name: SignalName,
     width: u32,
+    width_param: Option<ParamName>,
+    signed: bool,
+    // outer packed dimensions, outermost first, 0 if unused
+    packed: [u32; MAX_PACKED],
+    depth: Option<u32>,
+    // interface instance the signal is a field of, `bus` for `bus.valid`
+    interface: Option<SignalName>,
 
-    pub module: Option<VModule<'module>>,
+    pub module: Option<ModuleId>,
 }
 
-impl Signal<'_> {
+impl Signal {
     pub fn new(name: &str, width: u32) -> Self {
-        let name = SignalName::try_from_str(name).expect("expected valid name");
+        Signal::try_new(name, width).or_panic()
+    }
+
+    /// Same as `new`, fails if the name is too long or the width is 0.
+    pub fn try_new(name: &str, width: u32) -> Result<Self, KungError> {
+        let name = SignalName::try_from_str(name)
+            .map_err(|_| KungError::NameTooLong(String::from(name)))?;
+        if width == 0 {
+            return Err(KungError::ZeroWidth(String::from(name.as_str())));
+        }
 
-        Signal{
-            name: name,
+        Ok(Signal{
+            name,
             width,
+            width_param: None,
+            signed: false,
+            packed: [0; MAX_PACKED],
+            depth: None,
+            interface: None,
             module: None,
-        }
+        })
     }
 
-    pub fn copy(&self) -> Self {
-        Signal::new(self.name(), self.width)
+    /// Signal declared with the parameter as its width, `logic [W-1:0]`.
+    pub fn new_param(name: &str, width: Param) -> Self {
+        Signal::try_new_param(name, width).or_panic()
     }
 
-    pub fn bool(name: &str) -> Self {
-        Signal::new(name, 1)
+    /// Same as `new_param`, fails if the name is too long or the parameter
+    /// is 0.
+    pub fn try_new_param(name: &str, width: Param) -> Result<Self, KungError> {
+        let mut sig = Signal::try_new(name, width.value() as u32)?;
+        sig.width_param = Some(ParamName::from_str_truncate(width.name()));
+        Ok(sig)
     }
 
-    pub fn def(&self) -> String {
-        let mut s = String::new();
-        s.push_str(&format!("logic [{}:0] {}", &(self.width - 1).to_string(), &self.name));
-        s
+    pub fn copy(&self) -> Self {
+        let mut sig = *self;
+        sig.module = None;
+        sig
     }
 
-    pub fn name(&self) -> &str {
-        self.name.as_str()
+    /// Name of the parameter the width is declared with.
+    pub fn width_param(&self) -> Option<&str> {
+        self.width_param.as_ref().map(|p| p.as_str())
     }
-}
 
-impl Operand for Signal<'_> {
-    fn repr(&self) -> String {
-        let mut s = String::new();
-        s.push_str(&format!("{}", &self.name));
-        s
+    /// Makes the signal signed, it is declared as `logic signed`.
+    pub fn signed(mut self) -> Self {
+        self.signed = true;
+        self
     }
-}
 
-impl<'module> Add<Signal<'module>> for Signal<'module> {
-    type Output = Op;
+    /// Packs `count` copies of the signal into an array, `logic [count-1:0][7:0]`.
+    pub fn packed(mut self, count: u32) -> Self {
+        let used = self.packed.iter().take_while(|dim| **dim != 0).count();
+        if used == MAX_PACKED {
+            panic!("'{}' can't have more than {} packed dimensions", self.name, MAX_PACKED + 1);
+        }
+        if count == 0 {
+            panic!("packed dimension of '{}' can't be empty", self.name);
+        }
 
-    fn add(self, other: Signal<'module>) -> Self::Output {
-        let cloned = Signal::new(self.name(), self.width);
-        let other = Signal::new(other.name(), other.width);
-        Op::new(Box::new(cloned), Box::new(other), "+")
+        self.packed.copy_within(0..used, 1);
+        self.packed[0] = count;
+        self.width *= count;
+        self
     }
-}
 
-#[duplicate(tt; [Op]; [u32]; [i32])]
-impl<'module> Add<tt> for Signal<'module> {
-    type Output = Op;
-
-    fn add(self, other: tt) -> Self::Output {
-        let cloned = Signal::new(self.name(), self.width);
-        Op::new(Box::new(cloned), Box::new(other), "+")
+    /// Makes the signal a memory of `depth` words, `logic [7:0] mem [0:depth-1]`.
+    /// Memories are read with `index` and written with `Assign::indexed`,
+    /// expressions using the whole memory panic.
+    pub fn depth(mut self, depth: u32) -> Self {
+        if depth == 0 {
+            panic!("memory '{}' can't be empty", self.name);
+        }
+        self.depth = Some(depth);
+        self
     }
-}
-
-/*
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl BitAnd<tt> for Signal {
-    type Output = Op;
 
-    fn bitand(self, other: tt) -> Self::Output {
-        return Op::new(self, other, "&");
+    /// Field `field` of the interface instance `interface`, named `interface.field`.
+    pub(crate) fn interface_field(interface: &str, field: &str, width: u32) -> Result<Self, KungError> {
+        let mut sig = Signal::try_new(&format!("{}.{}", interface, field), width)?;
+        sig.interface = Some(SignalName::from_str_truncate(interface));
+        Ok(sig)
     }
-}
 
-impl Not for Signal {
-    type Output = Op;
-
-    fn not(self) -> Self::Output {
-        return Op::new_unary(self, "~");
+    /// Name of the interface instance the signal is a field of.
+    pub fn interface(&self) -> Option<&str> {
+        self.interface.as_ref().map(|name| name.as_str())
     }
-}
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl BitOr<tt> for Signal {
-    type Output = Op;
-
-    fn bitor(self, other: tt) -> Self::Output {
-        return Op::new(self, other, "|");
+    /// Number of words if the signal is a memory.
+    pub fn words(&self) -> Option<u32> {
+        self.depth
     }
-}
-
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl BitXor<tt> for Signal {
-    type Output = Op;
 
-    fn bitxor(self, other: tt) -> Self::Output {
-        return Op::new(self, other, "^");
+    /// Width of the values selected by `index`: a word of a memory, the next
+    /// packed dimension, or a single bit.
+    pub fn element_width(&self) -> u32 {
+        if self.depth.is_some() {
+            self.width
+        } else {
+            self.width.checked_div(self.packed[0]).unwrap_or(1)
+        }
     }
-}
-
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Sub<tt> for Signal {
-    type Output = Op;
 
-    fn sub(self, other: tt) -> Self::Output {
-        return Op::new(self, other, "-");
+    pub fn bool(name: &str) -> Self {
+        Signal::new(name, 1)
     }
-}
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Mul<tt> for Signal {
-    type Output = Op;
+    pub fn def(&self) -> String {
+        let signed = if self.signed { " signed" } else { "" };
+        let mut dims = String::new();
+        for dim in self.packed.iter().take_while(|dim| **dim != 0) {
+            dims.push_str(&format!("[{}:0]", dim - 1));
+        }
 
-    fn mul(self, other: tt) -> Self::Output {
-        return Op::new(self, other, "*");
-    }
-}
+        let elem = self.packed.iter().filter(|dim| **dim != 0).product::<u32>();
+        match &self.width_param {
+            Some(param) => dims.push_str(&format!("[{}-1:0]", param)),
+            None => dims.push_str(&format!("[{}:0]", self.width / elem - 1)),
+        }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Div<tt> for Signal {
-    type Output = Op;
+        match self.depth {
+            Some(depth) => format!("logic{} {} {} [0:{}]", signed, dims, &self.name, depth - 1),
+            None => format!("logic{} {} {}", signed, dims, &self.name),
+        }
+    }
 
-    fn div(self, other: tt) -> Self::Output {
-        return Op::new(self, other, "/");
+    pub fn name(&self) -> &str {
+        self.name.as_str()
     }
 }
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Shl<tt> for Signal {
-    type Output = Op;
+impl Operand for Signal {
+    fn repr(&self) -> String {
+        self.name.to_string()
+    }
 
-    fn shl(self, other: tt) -> Self::Output {
-        return Op::new(self, other, "<<");
+    fn width(&self) -> u32 {
+        self.width
     }
-}
 
-#[duplicate(tt; [Signal]; [Op]; [u32]; [i32])]
-impl Shr<tt> for Signal {
-    type Output = Op;
+    fn is_signed(&self) -> bool {
+        self.signed
+    }
 
-    fn shr(self, other: tt) -> Self::Output {
-        return Op::new(self, other, ">>");
+    fn collect_signals(&self, signals: &mut Vec<Signal>) {
+        signals.push(*self);
     }
-}
 
-impl Signal {
-    pub fn cmp(&self, other: Box<dyn Operand>, op: &str) -> bool {
-        let cond = Condition::signal_based(*self, other, op);
-        Condition::push_last(cond);
-        return false
+    fn as_signal(&self) -> Option<Signal> {
+        Some(*self)
     }
 }
 
-#[duplicate(tt; [Signal]; [u32]; [i32])]
-impl PartialEq<tt> for Signal {
-    fn eq(&self, other: &tt) -> bool {
-        return Signal::cmp(self, Box::new(*other), "==")
-    }
+#[duplicate(
+    trait_name  method  op;
+    [Add]       [add]       ["+"];
+    [Sub]       [sub]       ["-"];
+    [Mul]       [mul]       ["*"];
+    [Div]       [div]       ["/"];
+    [Shl]       [shl]       ["<<"];
+    [Shr]       [shr]       [">>"];
+    [BitAnd]    [bitand]    ["&"];
+    [BitOr]     [bitor]     ["|"];
+    [BitXor]    [bitxor]    ["^"];
+)]
+#[duplicate(tt; [Signal]; [Op]; [u32]; [i32]; [Const]; [Param])]
+impl trait_name<tt> for Signal {
+    type Output = Op;
 
-    fn ne(&self, other: &tt) -> bool {
-        return Signal::cmp(self, Box::new(*other), "!=")
+    fn method(self, other: tt) -> Self::Output {
+        Op::new(Box::new(self), Box::new(other), op)
     }
 }
 
-#[duplicate(tt; [Signal]; [u32]; [i32])]
-impl PartialOrd<tt> for Signal {
-    fn partial_cmp(&self, _other: &tt) -> Option<Ordering> {
-        Some(Ordering::Equal)
-    }
+impl Not for Signal {
+    type Output = Op;
 
-    fn lt(&self, other: &tt) -> bool {
-        return Signal::cmp(self, Box::new(*other), "<")
-    }
-    fn le(&self, other: &tt) -> bool {
-        return Signal::cmp(self, Box::new(*other), "<=")
-    }
-    fn gt(&self, other: &tt) -> bool {
-        return Signal::cmp(self, Box::new(*other), ">")
-    }
-    fn ge(&self, other: &tt) -> bool {
-        return Signal::cmp(self, Box::new(*other), ">=")
+    fn not(self) -> Self::Output {
+        Op::new_unary(Box::new(self), "~")
     }
 }
-*/
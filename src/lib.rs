@@ -1,2 +1,9 @@
+// lets the code generated by the procedural macros refer to `::kung` here too
+extern crate self as kung;
+
 pub mod hdl;
-pub mod tests;
+
+pub use kung_macros::hdl;
+
+#[cfg(test)]
+mod tests;
@@ -1,6 +1,4 @@
-#[cfg(test)]
-
-use crate::comb;
+use crate::{cat, comb, hdl, seq};
 use crate::hdl::*;
 
 #[test]
@@ -12,13 +10,14 @@ fn simple_adder() {
     let o = m.logic("o", 32);
 
     m += a;
-    //m += b;
-    //m -= o;
+    m += b;
+    m -= o;
+    m += expr::Assign::new(&o, c + 1);
+    m += expr::Assign::new(&c, a + b);
 
-    assert_eq!(m.synth(), "module adder();\ninput logic [31:0] a;\ninput logic [31:0] b;\noutput logic [31:0] o;\nassign o = (c + 1);\nassign c = (a + b);\nendmodule\n");
+    assert_eq!(m.synth(), "module adder(input logic [31:0] a, input logic [31:0] b, output logic [31:0] o);\nlogic [31:0] c;\nassign c = (a + b);\nassign o = (c + 1);\nendmodule\n");
 }
 
-    /*
 #[test]
 fn ops() {
     use crate::hdl::expr::Op;
@@ -60,69 +59,1169 @@ fn ops() {
     assert_eq!(((a - b) ^ (a + b)).repr(), "((a - b) ^ (a + b))");
     assert_eq!((!(a - b)).repr(), "(~(a - b))");
     assert_eq!(((a - b) - 1u32).repr(), "((a - b) - 1)");
+    assert_eq!((1u32 + a).repr(), "(1 + a)");
+    assert_eq!((2u32 * (a - b)).repr(), "(2 * (a - b))");
+}
+
+#[test]
+fn comparisons() {
+    let a = Signal::new("a", 32);
+    let b = Signal::new("b", 32);
+
+    assert_eq!(a.eq(b).repr(), "(a == b)");
+    assert_eq!(a.ne(1).repr(), "(a != 1)");
+    assert_eq!(a.lt(1).repr(), "(a < 1)");
+    assert_eq!(a.le(b).repr(), "(a <= b)");
+    assert_eq!(a.gt(1u32).repr(), "(a > 1)");
+    assert_eq!(a.ge(b).repr(), "(a >= b)");
+    assert_eq!((a + 1u32).eq(b).repr(), "((a + 1) == b)");
+    assert_eq!(a.eq(1).and(b.lt(a + 1)).repr(), "((a == 1) && (b < (a + 1)))");
+    assert_eq!(a.eq(1).or(b.ge(2)).logical_not().repr(), "(!((a == 1) || (b >= 2)))");
+    assert_eq!((!a.eq(1)).repr(), "(~(a == 1))");
+}
+
+#[test]
+fn when_conditions() {
+    let a = Signal::new("a", 32);
+    let mut s = Scope::new();
+
+    s.when(a.eq(1), |_| {}).elsewhen(a.gt(2), |_| {}).otherwise(|_| {});
+
+    assert_eq!(s.synth(), "always_comb begin\nif ((a == 1)) begin\nend\nelse if ((a > 2)) begin\nend\nelse begin\nend\nend\n");
+}
+
+#[test]
+fn widths() {
+    let a = Signal::new("a", 32);
+    let b = Signal::new("b", 16);
+    let c = Signal::new("c", 8);
+
+    assert_eq!((a + b).width(), 32);
+    assert_eq!((b - c).width(), 16);
+    assert_eq!((b * c).width(), 24);
+    assert_eq!((b / c).width(), 16);
+    assert_eq!((c << a).width(), 8);
+    assert_eq!((b & c).width(), 16);
+    assert_eq!((!c).width(), 8);
+    assert_eq!((c + 1u32).width(), 8);
+    assert_eq!((c + 1024u32).width(), 11);
+    assert_eq!((Const::new(1u32, 32) << c).width(), 32);
+    assert_eq!(a.eq(b).width(), 1);
+    assert_eq!(a.lt(b).or(c.ge(1)).width(), 1);
+
+    assert_eq!(a.trunc(16).repr(), "16'(a)");
+    assert_eq!((b + c).zext(32).repr(), "32'(b + c)");
+    assert_eq!(c.sext(16).repr(), "16'($signed(c))");
+    assert_eq!((b + c).resize(8).width(), 8);
+    assert_eq!((b * c).resize(16).repr(), "16'(b * c)");
+    assert_eq!(expr::Op::wrap(Box::new(String::from("(a) + (b)"))).as_signed().repr(), "$signed((a) + (b))");
+}
+
+#[test]
+fn assign_width() {
+    let mut m = Module::new("widths");
+    let a = m.logic("a", 16);
+    let b = m.logic("b", 16);
+    let o = m.logic("o", 32);
+
+    m += a;
+    m += b;
+    m -= o;
+    m += expr::Assign::new(&o, (a * b).resize(32));
+    assert_eq!(m.synth(), "module widths(input logic [15:0] a, input logic [15:0] b, output logic [31:0] o);\nassign o = 32'(a * b);\nendmodule\n");
+}
+
+#[test]
+#[should_panic(expected = "width mismatch")]
+fn assign_width_mismatch() {
+    let a = Signal::new("a", 16);
+    let b = Signal::new("b", 16);
+    let o = Signal::new("o", 16);
+
+    expr::Assign::new(&o, a * b);
+}
+
+#[test]
+#[should_panic(expected = "can't truncate")]
+fn bad_trunc() {
+    let a = Signal::new("a", 16);
+    a.trunc(17);
+}
+
+#[test]
+fn slices() {
+    let a = Signal::new("a", 32);
+    let b = Signal::new("b", 8);
+
+    assert_eq!(a.slice(7, 0).repr(), "a[7:0]");
+    assert_eq!(a.slice(31, 16).width(), 16);
+    assert_eq!(a.bit(3).repr(), "a[3]");
+    assert_eq!(a.bit(3).width(), 1);
+    assert_eq!((a + b).slice(15, 8).repr(), "8'((a + b) >> 8)");
+    assert_eq!((a + b).bit(0).repr(), "1'(a + b)");
+
+    assert_eq!(a.concat(b).repr(), "{a, b}");
+    assert_eq!(a.concat(b).width(), 40);
+    assert_eq!(cat!(b, a.slice(3, 0), a.bit(31)).repr(), "{b, a[3:0], a[31]}");
+    assert_eq!(cat!(b, a.slice(3, 0), a.bit(31)).width(), 13);
+    assert_eq!(b.replicate(4).repr(), "{4{b}}");
+    assert_eq!(b.replicate(4).width(), 32);
+    assert_eq!(a.bit(31).replicate(24).concat(b).repr(), "{{24{a[31]}}, b}");
+    assert_eq!((a + cat!(b, b)).repr(), "(a + {b, b})");
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn slice_out_of_range() {
+    let a = Signal::new("a", 32);
+    a.slice(32, 0);
+}
+
+#[test]
+#[should_panic(expected = "index 40 is out of range for 'a' of 4 elements")]
+fn index_out_of_range() {
+    let a = Signal::new("a", 4);
+    a.index(40u32);
+}
+
+#[test]
+#[should_panic(expected = "'1' has no width in the concatenation")]
+fn concat_unsized() {
+    let a = Signal::new("a", 4);
+    cat!(a, 1u32);
+}
+
+#[test]
+fn constants() {
+    let a = Signal::new("a", 32);
+    let b = Signal::new("b", 8);
+
+    assert_eq!(Const::new(0xdead_beefu32, 32).repr(), "32'hdeadbeef");
+    assert_eq!(Const::new(5u32, 4).radix(Radix::Bin).repr(), "4'b101");
+    assert_eq!(Const::new(10u32, 8).radix(Radix::Dec).repr(), "8'd10");
+    assert_eq!(Const::new(8u32, 6).radix(Radix::Oct).repr(), "6'o10");
+    assert_eq!(Const::signed(-5, 8).repr(), "8'shfb");
+    assert_eq!(Const::signed(-5, 8).radix(Radix::Dec).repr(), "-8'sd5");
+    assert_eq!(Const::signed(3, 8).repr(), "8'sh3");
+
+    let wide = Const::from_str_radix("ffff_ffff_ffff_ffff_ffff_ffff_ffff_ffff", 16, 128);
+    assert_eq!(wide.repr(), "128'hffffffffffffffffffffffffffffffff");
+    assert_eq!(wide.width(), 128);
+
+    assert_eq!((a + Const::new(1u32, 32)).repr(), "(a + 32'h1)");
+    assert_eq!((Const::new(1u32, 40) + a).width(), 40);
+    assert_eq!(cat!(Const::new(0u32, 4), b).repr(), "{4'h0, b}");
+    assert_eq!(cat!(Const::new(0u32, 4), b).width(), 12);
+}
+
+#[test]
+#[should_panic(expected = "doesn't fit")]
+fn const_overflow() {
+    Const::new(256u32, 8);
+}
+
+#[test]
+#[should_panic(expected = "doesn't fit")]
+fn signed_const_overflow() {
+    Const::signed(-129, 8);
+}
+
+#[test]
+fn signed() {
+    let s = Signal::new("s", 16).signed();
+    let t = Signal::new("t", 16).signed();
+    let u = Signal::new("u", 16);
+
+    assert_eq!(s.def(), "logic signed [15:0] s");
+    assert_eq!(u.def(), "logic [15:0] u");
+
+    assert!((s + t).is_signed());
+    assert!((s * -3i32).is_signed());
+    assert!(!(s + u).is_signed());
+    assert!(!s.lt(t).is_signed());
+    assert!(!s.slice(7, 0).is_signed());
+
+    assert_eq!((s >> 2u32).repr(), "(s >>> 2)");
+    assert_eq!((u >> 2u32).repr(), "(u >> 2)");
+    assert_eq!((s << 2u32).repr(), "(s << 2)");
+    assert_eq!(((s + t) >> u).repr(), "((s + t) >>> u)");
+
+    assert_eq!(u.as_signed().repr(), "$signed(u)");
+    assert!(u.as_signed().is_signed());
+    assert_eq!((s + t).as_unsigned().repr(), "$unsigned(s + t)");
+    assert_eq!((u.as_signed() + s).repr(), "($signed(u) + s)");
+    assert_eq!(u.as_signed().lt(s).repr(), "($signed(u) < s)");
+
+    assert_eq!(s.resize(32).repr(), "32'(s)");
+    assert_eq!(s.sext(32).repr(), "32'(s)");
+    assert_eq!(s.zext(32).repr(), "32'($unsigned(s))");
+    assert_eq!(u.sext(32).repr(), "32'($signed(u))");
+    assert_eq!((s >> 4u32).slice(7, 0).repr(), "8'($unsigned(s >>> 4))");
+
+    let mut m = Module::new("signed");
+    let a = m.logic("a", 8).signed();
+    m += a;
+    assert_eq!(m.synth(), "module signed(input logic signed [7:0] a);\nendmodule\n");
+
+    // signed() after logic() is declared like logic_signed()
+    let m = Module::new("shift");
+    let a = m.logic("a", 8);
+    let t = m.logic("t", 8).signed();
+    let o = m.logic_signed("o", 8);
+    comb!(t := a.as_signed() >> 1u32).unwrap();
+    comb!(o := t >> 1u32).unwrap();
+    let synth = m.synth();
+    assert!(synth.contains("logic signed [7:0] t;\n"));
+    assert!(synth.contains("logic signed [7:0] o;\n"));
+    assert!(synth.contains("assign o = (t >>> 1);\n"));
+}
+
+#[test]
+fn muxes() {
+    use crate::hdl::expr::{mux, ternary, Op};
+
+    let a = Signal::new("a", 8);
+    let b = Signal::new("b", 8);
+    let c = Signal::new("c", 8);
+    let d = Signal::new("d", 8);
+    let e = Signal::new("e", 16);
+    let sel = Signal::new("sel", 2);
+    let en = Signal::bool("en");
+
+    assert_eq!(ternary(en, a, b).repr(), "(en ? a : b)");
+    assert_eq!(ternary(a.gt(b), a + 1u32, b).repr(), "((a > b) ? (a + 1) : b)");
+    assert_eq!(ternary(en, a, e).width(), 16);
+    assert_eq!((ternary(en, a, b) + c).repr(), "((en ? a : b) + c)");
+
+    assert_eq!(mux(sel, [a, b, c, d]).repr(), "(sel[1] ? (sel[0] ? d : c) : (sel[0] ? b : a))");
+    assert_eq!(mux(sel, [a, b, c]).repr(), "(sel[1] ? c : (sel[0] ? b : a))");
+    assert_eq!(mux(sel, [a]).repr(), "a");
+    assert_eq!(mux(sel, [a, b, c, d]).width(), 8);
+
+    let mut m = Module::new("mux");
+    let o = m.logic("o", 8);
+    m -= o;
+    m += expr::Assign::new(&o, mux(sel, [a.into(), b + c, Op::from(d)]));
+    assert_eq!(m.synth(), "module mux(output logic [7:0] o);\nassign o = (sel[1] ? d : (sel[0] ? (b + c) : a));\nendmodule\n");
+}
+
+#[test]
+#[should_panic(expected = "can't be selected")]
+fn mux_too_many_options() {
+    let a = Signal::new("a", 8);
+    let sel = Signal::bool("sel");
+
+    expr::mux(sel, [a, a, a]);
+}
+
+#[test]
+fn reductions() {
+    let a = Signal::new("a", 4);
+    let b = Signal::new("b", 4);
+
+    assert_eq!(a.reduce_and().repr(), "(&a)");
+    assert_eq!(a.reduce_or().repr(), "(|a)");
+    assert_eq!(a.reduce_xor().repr(), "(^a)");
+    assert_eq!(a.reduce_nand().repr(), "(~&a)");
+    assert_eq!(a.reduce_nor().repr(), "(~|a)");
+    assert_eq!(a.reduce_xnor().repr(), "(~^a)");
+    assert_eq!(a.reduce_or().width(), 1);
+    assert_eq!((a & b).reduce_xor().repr(), "(^(a & b))");
+    assert_eq!((a & b).reduce_xor().width(), 1);
+}
+
+#[test]
+fn bit_helpers() {
+    let a = Signal::new("a", 4);
+
+    assert_eq!(a.popcount().repr(), "((3'(a[0]) + 3'(a[1])) + (3'(a[2]) + 3'(a[3])))");
+    assert_eq!(a.popcount().width(), 3);
+    assert_eq!(a.leading_zeros().repr(), "(a[3] ? 3'h0 : (a[2] ? 3'h1 : (a[1] ? 3'h2 : (a[0] ? 3'h3 : 3'h4))))");
+    assert_eq!(a.leading_zeros().width(), 3);
+    assert_eq!(a.reverse_bits().repr(), "{a[0], a[1], a[2], a[3]}");
+    assert_eq!(a.reverse_bits().width(), 4);
+    assert_eq!(a.onehot_to_binary().repr(), "{(a[2] | a[3]), (a[1] | a[3])}");
+    assert_eq!(a.onehot_to_binary().width(), 2);
+    assert_eq!(Signal::new("h", 3).onehot_to_binary().repr(), "{h[2], h[1]}");
+}
+
+#[test]
+fn ports() {
+    let mut m = Module::new("ports");
+    let a = m.logic("a", 8);
+    let b = m.logic("b", 8);
+    let t = m.logic("t", 9);
+    let o = m.logic("o", 8);
+
+    m -= o;
+    m += b;
+    m += a;
+    m += expr::Assign::new(&t, a.zext(9) + b);
+    m += expr::Assign::new(&o, t.slice(8, 1));
+
+    let ports: Vec<(Direction, String)> = m.borrow().ports().iter()
+        .map(|(dir, sig)| (*dir, String::from(sig.name())))
+        .collect();
+    assert_eq!(ports, vec![
+        (Direction::Input, String::from("a")),
+        (Direction::Input, String::from("b")),
+        (Direction::Output, String::from("o")),
+    ]);
+    assert_eq!(m.borrow().nets().len(), 1);
+    assert_eq!(m.borrow().nets()[0].name(), "t");
+
+    assert_eq!(m.synth(), "module ports(input logic [7:0] a, input logic [7:0] b, output logic [7:0] o);\nlogic [8:0] t;\nassign o = t[8:1];\nassign t = (9'(a) + b);\nendmodule\n");
+}
+
+#[test]
+fn internal_signals() {
+    let mut m = Module::new("internal");
+    let a = m.logic("a", 8);
+    let b = m.logic("b", 8);
+    m.logic("unused", 4);
+    let floating = m.logic("floating", 8);
+    let t = m.logic("t", 8);
+    let o = m.logic("o", 8);
+    let x = m.logic("x", 8);
+
+    m += a;
+    m += b;
+    m -= o;
+    m -= x;
+    m += expr::Assign::new(&t, a + floating);
+    m += expr::Assign::new(&o, t.into());
+
+    assert_eq!(m.synth(), "module internal(input logic [7:0] a, input logic [7:0] b, output logic [7:0] o, output logic [7:0] x);\nlogic [7:0] floating;\nlogic [7:0] t;\nlogic [3:0] unused;\nassign o = t;\nassign t = (a + floating);\nendmodule\n");
+    assert_eq!(m.borrow().warnings(), vec![
+        "'floating' is never driven in module 'internal'",
+        "'unused' is never driven in module 'internal'",
+        "'x' is never driven in module 'internal'",
+        "'unused' is never read in module 'internal'",
+        "'b' is never read in module 'internal'",
+    ]);
+}
+
+#[test]
+#[should_panic(expected = "already defined")]
+fn duplicate_signal() {
+    let m = Module::new("dup");
+    m.logic("a", 8);
+    m.logic("a", 4);
+}
+
+fn adder(width: u32) -> VModule {
+    let mut m = Module::new("adder");
+    let a = m.logic("a", width);
+    let b = m.logic("b", width);
+    let o = m.logic("o", width);
+
+    m += a;
+    m += b;
+    m -= o;
+    m += expr::Assign::new(&o, a + b);
+    m
+}
+
+#[test]
+fn instances() {
+    let child = adder(8);
+
+    let mut m = Module::new("top");
+    let x = m.logic("x", 8);
+    let y = m.logic("y", 8);
+    let z = m.logic("z", 8);
+    let s = m.logic("s", 8);
+
+    m += x;
+    m += y;
+    m -= z;
+    m.borrow_mut().instance(&child, "u_first", [("a", x.into()), ("b", y.into()), ("o", s.into())]);
+    m.borrow_mut().instance(&child, "u_second", vec![("o", z.into()), ("a", s.into()), ("b", y + 1u32)]);
+
+    assert_eq!(m.synth(), "module top(input logic [7:0] x, input logic [7:0] y, output logic [7:0] z);\nlogic [7:0] s;\nadder u_first (.a(x), .b(y), .o(s));\nadder u_second (.a(s), .b(y + 1), .o(z));\nendmodule\n");
+    assert!(m.borrow().warnings().is_empty());
+    assert_eq!(m.borrow().instances().len(), 2);
+    assert_eq!(m.borrow().instances()[0].module().borrow().name(), "adder");
+}
+
+#[test]
+#[should_panic(expected = "is not connected")]
+fn instance_missing_port() {
+    let child = adder(8);
+    let m = Module::new("top");
+    let x = m.logic("x", 8);
+
+    m.borrow_mut().instance(&child, "u_add", [("a", x.into()), ("b", x.into())]);
+}
+
+#[test]
+#[should_panic(expected = "width mismatch")]
+fn instance_width_mismatch() {
+    let child = adder(8);
+    let m = Module::new("top");
+    let x = m.logic("x", 8);
+    let o = m.logic("o", 16);
+
+    m.borrow_mut().instance(&child, "u_add", [("a", x.into()), ("b", x.into()), ("o", o.into())]);
+}
+
+#[test]
+#[should_panic(expected = "can only be connected to a signal")]
+fn instance_output_expression() {
+    let child = adder(8);
+    let m = Module::new("top");
+    let x = m.logic("x", 8);
+
+    m.borrow_mut().instance(&child, "u_add", [("a", x.into()), ("b", x.into()), ("o", x + 1u32)]);
+}
+
+#[test]
+#[should_panic(expected = "drives input")]
+fn instance_drives_input() {
+    let child = adder(8);
+    let mut m = Module::new("top");
+    let x = m.logic("x", 8);
+
+    m += x;
+    m.borrow_mut().instance(&child, "u_add", [("a", x.into()), ("b", x.into()), ("o", x.into())]);
+}
+
+#[test]
+#[should_panic(expected = "has no port named")]
+fn instance_unknown_port() {
+    let child = adder(8);
+    let m = Module::new("top");
+    let x = m.logic("x", 8);
+
+    m.borrow_mut().instance(&child, "u_add", [("a", x.into()), ("b", x.into()), ("o", x.into()), ("c", x.into())]);
+}
+
+#[test]
+fn generators() {
+    use std::rc::Rc;
+
+    let mut reg = Registry::new();
+    let a8 = reg.module("adder", &[("w", 8)], |_| adder(8));
+    let again = reg.module("adder", &[("w", 8)], |_| panic!("variant should be cached"));
+    let a32 = reg.module("adder", &[("w", 32)], |_| adder(32));
+
+    assert!(Rc::ptr_eq(&a8, &again));
+    assert_eq!(a8.borrow().name(), "adder_w8");
+    assert_eq!(a32.borrow().name(), "adder_w32");
+
+    let top = reg.module("top", &[], |reg| {
+        let child = reg.module("adder", &[("w", 8)], |_| adder(8));
+        let mut m = Module::new("top");
+        let x = m.logic("x", 8);
+        let z = m.logic("z", 8);
+
+        m += x;
+        m -= z;
+        m.borrow_mut().instance(&child, "u_add", [("a", x.into()), ("b", x.into()), ("o", z.into())]);
+        m
+    });
+    assert_eq!(top.borrow().name(), "top");
+    assert_eq!(reg.modules().len(), 3);
+
+    assert_eq!(reg.synth(), "module adder_w8(input logic [7:0] a, input logic [7:0] b, output logic [7:0] o);\nassign o = (a + b);\nendmodule\n\n\
+module adder_w32(input logic [31:0] a, input logic [31:0] b, output logic [31:0] o);\nassign o = (a + b);\nendmodule\n\n\
+module top(input logic [7:0] x, output logic [7:0] z);\nadder_w8 u_add (.a(x), .b(x), .o(z));\nendmodule\n");
+}
+
+#[test]
+fn generator_name_clash() {
+    let mut reg = Registry::new();
+    let first = reg.module("adder_w8", &[], |_| adder(8));
+    let second = reg.module("adder", &[("w", 8)], |_| adder(8));
+
+    assert_eq!(first.borrow().name(), "adder_w8");
+    assert_eq!(second.borrow().name(), "adder_w8_1");
+}
+
+fn param_adder() -> VModule {
+    let mut m = Module::new("adder");
+    let width = m.borrow_mut().param("WIDTH", 32);
+    let last = m.borrow_mut().localparam("LAST", 31);
+    let a = m.logic_param("a", width);
+    let b = m.logic_param("b", width);
+    let o = m.logic_param("o", width);
+
+    m += a;
+    m += b;
+    m -= o;
+    m += expr::Assign::new(&o, a + b + last);
+    m
+}
+
+#[test]
+fn params() {
+    let child = param_adder();
+    assert_eq!(child.synth(), "module adder #(parameter WIDTH = 32) (input logic [WIDTH-1:0] a, input logic [WIDTH-1:0] b, output logic [WIDTH-1:0] o);\nlocalparam LAST = 31;\nassign o = ((a + b) + LAST);\nendmodule\n");
+
+    let mut m = Module::new("top");
+    let x = m.logic("x", 16);
+    let y = m.logic("y", 16);
+    let z = m.logic("z", 16);
+    let w = m.logic("w", 32);
+
+    m += x;
+    m += y;
+    m -= z;
+    m -= w;
+    m.borrow_mut().instance_with_params(&child, "u_narrow", &[("WIDTH", 16)], [("a", x.into()), ("b", y.into()), ("o", z.into())]);
+    m.borrow_mut().instance(&child, "u_wide", [("a", x.zext(32)), ("b", y.zext(32)), ("o", w.into())]);
+
+    assert_eq!(m.synth(), "module top(input logic [15:0] x, input logic [15:0] y, output logic [31:0] w, output logic [15:0] z);\nadder #(.WIDTH(16)) u_narrow (.a(x), .b(y), .o(z));\nadder u_wide (.a(32'(x)), .b(32'(y)), .o(w));\nendmodule\n");
+}
+
+#[test]
+#[should_panic(expected = "width mismatch")]
+fn param_width_mismatch() {
+    let child = param_adder();
+    let m = Module::new("top");
+    let x = m.logic("x", 16);
+
+    m.borrow_mut().instance(&child, "u_add", [("a", x.into()), ("b", x.into()), ("o", x.into())]);
+}
+
+#[test]
+#[should_panic(expected = "has no parameter named 'LAST'")]
+fn localparam_override() {
+    let child = param_adder();
+    let m = Module::new("top");
+    let x = m.logic("x", 16);
+
+    m.borrow_mut().instance_with_params(&child, "u_add", &[("LAST", 16)], [("a", x.into()), ("b", x.into()), ("o", x.into())]);
+}
+
+#[test]
+fn design() {
+    let leaf = adder(8);
+
+    let mut mid = Module::new("mid");
+    let a = mid.logic("a", 8);
+    let o = mid.logic("o", 8);
+    mid += a;
+    mid -= o;
+    mid.borrow_mut().instance(&leaf, "u_add", [("a", a.into()), ("b", a.into()), ("o", o.into())]);
+
+    let mut top = Module::new("top");
+    let x = top.logic("x", 8);
+    let y = top.logic("y", 8);
+    let z = top.logic("z", 8);
+    top += x;
+    top -= y;
+    top -= z;
+    top.borrow_mut().instance(&mid, "u_mid", [("a", x.into()), ("o", y.into())]);
+    top.borrow_mut().instance(&leaf, "u_add", [("a", x.into()), ("b", y.into()), ("o", z.into())]);
+
+    let design = Design::new(&top);
+    let names: Vec<String> = design.modules().iter().map(|m| m.borrow().name().to_string()).collect();
+    assert_eq!(names, ["adder", "mid", "top"]);
+    assert_eq!(design.synth(), [leaf.synth(), mid.synth(), top.synth()].join("\n"));
+
+    let dir = std::env::temp_dir().join(format!("kung_design_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let list = design.write_files(&dir).unwrap();
+    assert_eq!(list, dir.join("top.f"));
+    let files: Vec<String> = ["adder", "mid", "top"].iter().map(|n| format!("{}\n", dir.join(format!("{}.sv", n)).display())).collect();
+    assert_eq!(std::fs::read_to_string(&list).unwrap(), files.concat());
+    assert_eq!(std::fs::read_to_string(dir.join("mid.sv")).unwrap(), mid.synth());
+
+    let list = design.write_file(&dir).unwrap();
+    assert_eq!(std::fs::read_to_string(&list).unwrap(), format!("{}\n", dir.join("top.sv").display()));
+    assert_eq!(std::fs::read_to_string(dir.join("top.sv")).unwrap(), design.synth());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "different modules named")]
+fn design_name_clash() {
+    let first = adder(8);
+    let second = adder(4);
+
+    let mut top = Module::new("top");
+    let x = top.logic("x", 8);
+    let y = top.logic("y", 4);
+    let p = top.logic("p", 8);
+    let q = top.logic("q", 4);
+    top += x;
+    top += y;
+    top.borrow_mut().instance(&first, "u_first", [("a", x.into()), ("b", x.into()), ("o", p.into())]);
+    top.borrow_mut().instance(&second, "u_second", [("a", y.into()), ("b", y.into()), ("o", q.into())]);
+
+    Design::new(&top).modules();
+}
+
+crate::bundle! {
+    struct Stream {
+        valid: output 1,
+        ready: input 1,
+        data: output 8,
+    }
+}
+
+fn pass(interfaces: bool) -> VModule {
+    let mut m = Module::new("pass");
+    let (src, dst): (Stream, Stream) = if interfaces {
+        (m.interface("src"), m.interface("dst"))
+    } else {
+        (m.bundle("src"), m.bundle("dst"))
+    };
+    let src = src.flip();
+
+    m.borrow_mut().port_bundle(&src);
+    m.borrow_mut().port_bundle(&dst);
+    m += expr::Assign::new(&dst.valid, src.valid.into());
+    m += expr::Assign::new(&dst.data, src.data.into());
+    m += expr::Assign::new(&src.ready, dst.ready.into());
+    m
+}
+
+#[test]
+fn bundles() {
+    let child = pass(false);
+    assert_eq!(child.synth(), "module pass(input logic [0:0] dst_ready, input logic [7:0] src_data, input logic [0:0] src_valid, output logic [7:0] dst_data, output logic [0:0] dst_valid, output logic [0:0] src_ready);\nassign dst_data = src_data;\nassign dst_valid = src_valid;\nassign src_ready = dst_ready;\nendmodule\n");
+    assert!(child.borrow().warnings().is_empty());
+
+    let m = Module::new("top");
+    let a: Stream = m.bundle("a");
+    let b: Stream = m.bundle("b");
+    m.borrow_mut().port_bundle(&a.flip());
+    m.borrow_mut().port_bundle(&b);
+    m.borrow_mut().instance(&child, "u_pass", a.connect(&child, "src").into_iter().chain(b.connect(&child, "dst")));
+
+    assert_eq!(m.synth(), "module top(input logic [7:0] a_data, input logic [0:0] a_valid, input logic [0:0] b_ready, output logic [0:0] a_ready, output logic [7:0] b_data, output logic [0:0] b_valid);\npass u_pass (.dst_ready(b_ready), .src_data(a_data), .src_valid(a_valid), .dst_data(b_data), .dst_valid(b_valid), .src_ready(a_ready));\nendmodule\n");
+    assert!(m.borrow().warnings().is_empty());
+}
+
+#[test]
+fn interfaces() {
+    assert_eq!(Stream::interface(), "interface Stream;\nlogic [0:0] valid;\nlogic [0:0] ready;\nlogic [7:0] data;\nmodport master(output valid, input ready, output data);\nmodport slave(input valid, output ready, input data);\nendinterface\n");
+
+    let child = pass(true);
+    assert_eq!(child.synth(), "module pass(Stream.slave src, Stream.master dst);\nassign dst.data = src.data;\nassign dst.valid = src.valid;\nassign src.ready = dst.ready;\nendmodule\n");
+
+    let m = Module::new("top");
+    let a: Stream = m.interface("a");
+    let b: Stream = m.interface("b");
+    let mid: Stream = m.interface("mid");
+    m.borrow_mut().port_bundle(&a.flip());
+    m.borrow_mut().port_bundle(&b);
+    m.borrow_mut().instance(&child, "u_first", a.connect(&child, "src").into_iter().chain(mid.connect(&child, "dst")));
+    m.borrow_mut().instance(&child, "u_second", mid.connect(&child, "src").into_iter().chain(b.connect(&child, "dst")));
+
+    assert_eq!(m.synth(), "module top(Stream.slave a, Stream.master b);\nStream mid();\npass u_first (.src(a), .dst(mid));\npass u_second (.src(mid), .dst(b));\nendmodule\n");
+
+    let design = Design::new(&m);
+    assert_eq!(design.synth(), [Stream::interface(), child.synth(), m.synth()].join("\n"));
+}
+
+#[test]
+#[should_panic(expected = "can only be connected to a whole interface")]
+fn interface_partial_connection() {
+    let child = pass(true);
+
+    let m = Module::new("top");
+    let a: Stream = m.interface("a");
+    let b: Stream = m.interface("b");
+    let c: Stream = m.bundle("c");
+    m.borrow_mut().port_bundle(&a.flip());
+    m.borrow_mut().port_bundle(&b);
+
+    let mut conns = a.connect(&child, "src");
+    conns[0].1 = c.valid.into();
+    m.borrow_mut().instance(&child, "u_pass", conns.into_iter().chain(b.connect(&child, "dst")));
+}
+
+#[test]
+fn interface_lookalike_signals() {
+    let child = pass(true);
+
+    let m = Module::new("top");
+    let b: Stream = m.interface("b");
+    let valid = m.logic("a.valid", 1);
+    let ready = m.logic("a.ready", 1);
+    let data = m.logic("a.data", 8);
+
+    let conns: Vec<(String, expr::Op)> = vec![
+        (String::from("src.valid"), valid.into()),
+        (String::from("src.ready"), ready.into()),
+        (String::from("src.data"), data.into()),
+    ];
+    let err = m.borrow_mut().try_instance(&child, "u_pass", conns.into_iter().chain(b.connect(&child, "dst")));
+    assert_eq!(err.unwrap_err().to_string(), "port 'src' of 'pass' is an interface, it can only be connected to a whole interface");
+    assert_eq!(b.valid.interface(), Some("b"));
+    assert_eq!(valid.interface(), None);
+}
+
+#[test]
+fn memories() {
+    let mut m = Module::new("ram");
+    let clk = m.logic("clk", 1);
+    let we = m.logic("we", 1);
+    let waddr = m.logic("waddr", 4);
+    let raddr = m.logic("raddr", 4);
+    let wdata = m.logic("wdata", 32);
+    let rdata = m.logic("rdata", 32);
+    let qdata = m.logic("qdata", 32);
+    let mem = m.memory("mem", 32, 16);
+
+    m += clk;
+    m += we;
+    m += waddr;
+    m += raddr;
+    m += wdata;
+    m -= rdata;
+    m -= qdata;
+
+    assert_eq!(mem.def(), "logic [31:0] mem [0:15]");
+    assert_eq!(mem.index(raddr + 1u32).repr(), "mem[(raddr + 1)]");
+    assert_eq!(mem.index(raddr).width(), 32);
+
+    m.borrow_mut().on(&clk, |s| {
+        s.when(we.into(), |s| {
+            s.write(&mem, waddr.into(), wdata.into());
+        });
+    });
+    m.borrow_mut().read_port(&rdata, &mem, raddr.into());
+    m.borrow_mut().read_port_on(&clk, &qdata, &mem, raddr.into());
+
+    assert_eq!(m.synth(), "module ram(input logic [0:0] clk, input logic [3:0] raddr, input logic [3:0] waddr, input logic [31:0] wdata, input logic [0:0] we, output logic [31:0] qdata, output logic [31:0] rdata);\nlogic [31:0] mem [0:15];\nassign rdata = mem[raddr];\n\nalways_ff @(posedge clk) begin\nif (we) begin\nmem[waddr] <= wdata;\nend\nend\n\n\nalways_ff @(posedge clk) begin\nqdata <= mem[raddr];\nend\n\nendmodule\n");
+    assert!(m.borrow().warnings().is_empty());
+}
+
+#[test]
+fn arrays() {
+    let m = Module::new("m");
+    let a = m.array("a", 8, &[2, 4]);
+    let i = m.logic("i", 1);
+
+    assert_eq!(a.def(), "logic [1:0][3:0][7:0] a");
+    assert_eq!(a.width(), 64);
+    assert_eq!(a.element_width(), 32);
+    assert_eq!(a.index(i).width(), 32);
+    assert_eq!(i.index(0u32).width(), 1);
+    assert_eq!(Signal::new("s", 4).signed().packed(3).def(), "logic signed [2:0][3:0] s");
+
+    let w = Param::new("W", 16);
+    assert_eq!(Signal::new_param("p", w).packed(2).def(), "logic [1:0][W-1:0] p");
+
+    let e = m.logic("e", 32);
+    assert_eq!(expr::Assign::indexed(&a, i.into(), e.into()).synth(true), "a[i] <= e;");
+}
+
+#[test]
+#[should_panic(expected = "can only be written by word")]
+fn memory_whole_write() {
+    let m = Module::new("m");
+    let mem = m.memory("mem", 8, 4);
+    let x = m.logic("x", 8);
+    expr::Assign::new(&mem, x.into());
+}
+
+#[test]
+#[should_panic(expected = "memory 'mem' can only be read by word")]
+fn memory_whole_read() {
+    let m = Module::new("m");
+    let mem = m.memory("mem", 8, 4);
+    let _ = mem + 1u32;
+}
+
+#[test]
+#[should_panic(expected = "memory 'mem' can only be read by word")]
+fn memory_slice() {
+    let m = Module::new("m");
+    let mem = m.memory("mem", 8, 4);
+    mem.slice(7, 0);
+}
+
+#[test]
+fn memory_init() {
+    let mut m = Module::new("tables");
+    let addr = m.logic("addr", 2);
+    let sine = m.logic("sine", 8);
+    let code = m.logic("code", 4);
+    let word = m.logic("word", 12);
+    let mem = m.memory("mem", 12, 4);
+    let ucode = m.memory("ucode", 4, 8);
+
+    m += addr;
+    m -= sine;
+    m -= code;
+    m -= word;
+
+    m.borrow_mut().readmem(&mem, "mem.hex", Radix::Hex, (0..3u32).map(|i| i * 0x101));
+    m.borrow_mut().init(&ucode, vec![3u32, 10]);
+    m.borrow_mut().rom(&sine, &addr, vec![0u32, 90, 127]);
+    m.borrow_mut().read_port(&word, &mem, addr.into());
+    m.borrow_mut().read_port(&code, &ucode, addr.into());
+
+    assert_eq!(m.synth(), "module tables(input logic [1:0] addr, output logic [3:0] code, output logic [7:0] sine, output logic [11:0] word);\nlogic [11:0] mem [0:3];\nlogic [3:0] ucode [0:7];\nassign code = ucode[addr];\nassign word = mem[addr];\ninitial $readmemh(\"mem.hex\", mem);\ninitial begin\nucode[0] = 4'h3;\nucode[1] = 4'ha;\nend\n\nalways_comb begin\ncase (addr)\n0: sine = 8'h0;\n1: sine = 8'h5a;\n2: sine = 8'h7f;\ndefault: sine = '0;\nendcase\nend\n\nendmodule\n");
+    assert_eq!(m.borrow().mem_files(), [(String::from("mem.hex"), String::from("000\n101\n202\n"))]);
+    assert!(m.borrow().warnings().is_empty());
+
+    let b = Module::new("bin");
+    let bits = b.memory("bits", 3, 2);
+    b.borrow_mut().readmem(&bits, "bits.bin", Radix::Bin, vec![1u32, 6]);
+    assert_eq!(b.borrow().mem_files()[0].1, "001\n110\n");
+}
+
+#[test]
+#[should_panic(expected = "don't fit in 'mem' of 2 words")]
+fn memory_init_too_long() {
+    let m = Module::new("m");
+    let mem = m.memory("mem", 8, 2);
+    m.borrow_mut().init(&mem, vec![1u32, 2, 3]);
+}
+
+#[test]
+#[should_panic(expected = "word 1 of 'data' (16) doesn't fit in 4 bits")]
+fn rom_word_too_wide() {
+    let m = Module::new("m");
+    let addr = m.logic("addr", 2);
+    let data = m.logic("data", 4);
+    m.borrow_mut().rom(&data, &addr, vec![15u32, 16]);
+}
+
+#[test]
+fn resets() {
+    let mut m = Module::new("counter");
+    let clk = m.logic("clk", 1);
+    let rst_n = m.logic("rst_n", 1);
+    let count = m.reg("count", 8, 5u32);
+    let last = m.logic("last", 8);
+
+    m += clk;
+    m += rst_n;
+    m -= count;
+    m -= last;
+
+    m.borrow_mut().on_reset(&clk, condition::Reset::AsyncLow(rst_n), |s| {
+        s.assign(&count, (count + 1u32).trunc(8));
+        s.assign(&last, count.into());
+    });
+
+    assert_eq!(m.synth(), "module counter(input logic [0:0] clk, input logic [0:0] rst_n, output logic [7:0] count, output logic [7:0] last);\n\nalways_ff @(posedge clk or negedge rst_n) begin\nif (!rst_n) begin\ncount <= 8'h5;\nlast <= 8'h0;\nend\nelse begin\ncount <= 8'(count + 1);\nlast <= count;\nend\nend\n\nendmodule\n");
+    assert!(m.borrow().warnings().is_empty());
+
+    let mut m = Module::new("flag");
+    let clk = m.logic("clk", 1);
+    let rst = m.logic("rst", 1);
+    let set = m.logic("set", 1);
+    let flag = m.reg("flag", 1, 1u32);
+
+    m += clk;
+    m += rst;
+    m += set;
+    m -= flag;
+
+    m.borrow_mut().on_reset(&clk, condition::Reset::Sync(rst), |s| {
+        s.assign(&flag, set.into());
+    });
+    assert!(m.synth().contains("always_ff @(posedge clk) begin\nif (rst) begin\nflag <= 1'h1;\nend\nelse begin\nflag <= set;\nend\nend\n"));
+
+    let high = condition::Reset::Async(rst);
+    assert_eq!(high.event().map(|(edge, sig)| (edge, sig.name().to_string())), Some((condition::Edge::Pos, String::from("rst"))));
+    assert_eq!(condition::Reset::SyncLow(rst_n).active(), "!rst_n");
+    assert!(condition::Reset::SyncLow(rst_n).event().is_none());
+}
+
+#[test]
+#[should_panic(expected = "doesn't fit in 4 bits")]
+fn reset_value_too_wide() {
+    let m = Module::new("m");
+    m.reg("r", 4, 16u32);
+}
+
+#[test]
+fn sensitivity() {
+    use crate::hdl::condition::Edge;
+
+    let mut m = Module::new("ddr");
+    let clk = m.logic("clk", 1);
+    let rst = m.logic("rst", 1);
+    let en = m.logic("en", 1);
+    let d = m.logic("d", 4);
+    let rise = m.logic("rise", 4);
+    let fall = m.logic("fall", 4);
+    let both = m.logic("both", 4);
+    let held = m.logic("held", 4);
+
+    m += clk;
+    m += rst;
+    m += en;
+    m += d;
+    m -= rise;
+    m -= fall;
+    m -= both;
+    m -= held;
+
+    m.borrow_mut().on(&clk, |s| { s.assign(&rise, d.into()); });
+    m.borrow_mut().on_negedge(&clk, |s| { s.assign(&fall, d.into()); });
+    m.borrow_mut().on_events(&[(Edge::Both, clk), (Edge::Pos, rst)], |s| { s.assign(&both, d.into()); });
+    m.borrow_mut().latch(|s| {
+        s.when(en.into(), |s| { s.assign(&held, d.into()); });
+    });
+
+    assert_eq!(m.synth(), "module ddr(input logic [0:0] clk, input logic [3:0] d, input logic [0:0] en, input logic [0:0] rst, output logic [3:0] both, output logic [3:0] fall, output logic [3:0] held, output logic [3:0] rise);\n\nalways_ff @(posedge clk) begin\nrise <= d;\nend\n\n\nalways_ff @(negedge clk) begin\nfall <= d;\nend\n\n\nalways_ff @(edge clk or posedge rst) begin\nboth <= d;\nend\n\n\nalways_latch begin\nif (en) begin\nheld <= d;\nend\nend\n\nendmodule\n");
+    assert!(m.borrow().warnings().is_empty());
+
+    let mut m = Module::new("neg");
+    let clk_n = m.logic("clk_n", 1);
+    let rst_n = m.logic("rst_n", 1);
+    let q = m.logic("q", 1);
+    m += clk_n;
+    m += rst_n;
+    m -= q;
+    m.borrow_mut().on_reset(&clk_n, condition::Reset::AsyncLow(rst_n), |s| { s.assign(&q, !q); });
+    assert!(m.synth().contains("always_ff @(posedge clk_n or negedge rst_n) begin\n"));
+}
+
+#[test]
+#[should_panic(expected = "needs at least one event")]
+fn no_events() {
+    let m = Module::new("m");
+    m.borrow_mut().on_events(&[], |_| {});
 }
 
 #[test]
 fn comb() {
-    let mut m = Module::new("comb");
+    let m = Module::new("comb");
     let a = Signal::new("a", 32);
     let b = Signal::new("b", 32);
     let c = Signal::new("c", 32);
 
     // always_comb
-    m.comb(|s| {
-        s[c] = a + 1;
-        s.when(a == 1, |s| {
-            s[b] = a + c;
+    m.borrow_mut().comb(|s| {
+        s.assign(&c, a + 1);
+        s.when(a.eq(1), |s| {
+            s.assign(&b, a + c);
         });
     });
 
-    assert_eq!(m.synth(), "module comb();\n\nalways_comb begin\nc = (a + 1);\nif ((a == 1)) begin\nb = (a + c);\nend\nend\n\nendmodule\n");
+    assert_eq!(m.synth(), "module comb();\nlogic [31:0] b;\nlogic [31:0] c;\n\nalways_comb begin\nc = (a + 1);\nif ((a == 1)) begin\nb = (a + c);\nend\nend\n\nendmodule\n");
 }
 
 #[test]
 fn sync() {
-    let mut m = Module::new("sync");
+    let m = Module::new("sync");
     let a = Signal::new("a", 32);
     let b = Signal::new("b", 32);
     let c = Signal::new("c", 32);
     let clk = Signal::bool("clk");
 
-    m.on(clk, |s| {
-        s[c] = a + 1;
-        s.when(a == 1, |s| {
-            s[b] = a + c;
+    m.borrow_mut().on(&clk, |s| {
+        s.assign(&c, a + 1);
+        s.when(a.eq(1), |s| {
+            s.assign(&b, a + c);
         }).otherwise(|s| {
-            s[b] = a + c + 2;
+            s.assign(&b, a + c + 2);
+        });
+    });
+
+    assert_eq!(m.synth(), "module sync();\nlogic [31:0] b;\nlogic [31:0] c;\n\nalways_ff @(posedge clk) begin\nc <= (a + 1);\nif ((a == 1)) begin\nb <= (a + c);\nend\nelse begin\nb <= ((a + c) + 2);\nend\nend\n\nendmodule\n");
+}
+
+#[test]
+fn chains() {
+    let m = Module::new("chains");
+    let a = m.logic("a", 4);
+    let b = m.logic("b", 4);
+    let o = m.logic("o", 4);
+    let p = m.logic("p", 4);
+
+    let mut s = Scope::new();
+    s.assign(&o, Const::new(0u32, 4).into());
+    s.when(a.eq(1), |s| {
+        s.assign(&o, a.into());
+        s.when(b.eq(2), |s| {
+            s.assign(&p, b.into());
+        }).otherwise(|s| {
+            s.assign(&p, a.into());
+        });
+    }).elsewhen(a.eq(2), |s| {
+        s.assign(&o, b.into());
+    }).otherwise(|s| {
+        *s += expr::Assign::new(&o, a ^ b);
+    });
+    s.assign(&p, a & b);
+
+    assert_eq!(s.synth(), "always_comb begin\no = 4'h0;\nif ((a == 1)) begin\no = a;\nif ((b == 2)) begin\np = b;\nend\nelse begin\np = a;\nend\nend\nelse if ((a == 2)) begin\no = b;\nend\nelse begin\no = (a ^ b);\nend\np = (a & b);\nend\n");
+}
+
+#[test]
+#[should_panic(expected = "width mismatch")]
+fn scope_width_mismatch() {
+    let m = Module::new("m");
+    let a = m.logic("a", 4);
+    let b = m.logic("b", 8);
+
+    m.borrow_mut().comb(|s| {
+        s.assign(&a, b.into());
+    });
+}
+
+#[test]
+#[should_panic(expected = "already defined in this scope")]
+fn scope_duplicate_assign() {
+    let mut s = Scope::new();
+    let a = Signal::new("a", 4);
+    s.assign(&a, a.into());
+    s.assign(&a, a.into());
+}
+
+#[test]
+fn scope_errors() {
+    let m = Module::new("m");
+    let clk = m.logic("clk", 1);
+    let a = m.logic("a", 4);
+    let b = m.logic("b", 8);
+    let sel = m.logic("sel", 1);
+
+    let errors = m.borrow_mut().try_comb(|s| {
+        s.assign(&a, b.into());
+        s.when(sel.into(), |s| {
+            s.assign(&b, b.into());
+            s.assign(&b, b.into());
         });
+        s.switch(a.into())
+            .is(16u32, |_| {})
+            .default(|s| { s.assign(&b, a.into()); });
+    }).unwrap_err();
+    let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+    assert_eq!(errors, vec![
+        "width mismatch: 'a' is 4 bits wide but 'b' is 8 bits, use resize() to convert",
+        "assign with destination 'b' already defined in this scope",
+        "width mismatch: case on 'a' is 4 bits wide but '16' is 5 bits, use resize() to convert",
+        "width mismatch: 'b' is 8 bits wide but 'a' is 4 bits, use resize() to convert",
+    ]);
+
+    let errors = m.borrow_mut().try_comb(|s| {
+        s.otherwise(|_| {});
+        s.elsewhen(sel.into(), |_| {});
+        let case = s.switch(a.into());
+        case.pattern("1?", |_| {})
+            .casez()
+            .pattern("1?2?", |_| {})
+            .pattern("1?", |_| {})
+            .is_any(Vec::<u32>::new(), |_| {})
+            .default(|_| {});
+        case.is(1u32, |_| {}).default(|_| {});
+    }).unwrap_err();
+    let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+    assert_eq!(errors, vec![
+        "otherwise has to follow when or elsewhen",
+        "elsewhen has to follow when or elsewhen",
+        "wildcard patterns need casez or casex",
+        "'2' is not a valid bit in the casez pattern '1?2?'",
+        "pattern '1?' has 2 bits but 'a' is 4 bits wide",
+        "case arm needs at least one value",
+        "case arms can't follow the default arm",
+        "case already has a default arm",
+    ]);
+
+    assert!(m.borrow_mut().try_on(&clk, |s| {
+        assert!(matches!(s.try_assign(&a, b.into()), Err(KungError::WidthMismatch { .. })));
+        assert!(s.try_assign(&a, b.slice(3, 0)).is_ok());
+        assert!(matches!(s.try_assign(&a, b.slice(3, 0)), Err(KungError::DuplicateAssign(_))));
+    }).is_ok());
+    assert_eq!(m.synth(), "module m();\nlogic [3:0] a;\nlogic [7:0] b;\nlogic [0:0] clk;\nlogic [0:0] sel;\n\nalways_ff @(posedge clk) begin\na <= b[3:0];\nend\n\nendmodule\n");
+}
+
+#[test]
+#[should_panic(expected = "otherwise has to follow when or elsewhen")]
+fn otherwise_without_when() {
+    let mut s = Scope::new();
+    s.otherwise(|_| {});
+}
+
+#[test]
+fn cases() {
+    let mut m = Module::new("fsm");
+    let clk = m.logic("clk", 1);
+    let go = m.logic("go", 1);
+    let state = m.logic("state", 2);
+    let out = m.logic("out", 4);
+    let req = m.logic("req", 4);
+    let grant = m.logic("grant", 2);
+
+    m += clk;
+    m += go;
+    m += req;
+    m -= out;
+    m -= grant;
+
+    m.borrow_mut().on(&clk, |s| {
+        s.switch(state.into()).unique()
+            .is(0u32, |s| {
+                s.when(go.into(), |s| { s.assign(&state, Const::new(1u32, 2).into()); });
+            })
+            .is_any(vec![1u32, 2], |s| { s.assign(&state, (state + 1u32).trunc(2)); })
+            .default(|s| { s.assign(&state, Const::new(0u32, 2).into()); });
     });
+    m.borrow_mut().comb(|s| {
+        s.assign(&out, Const::new(0u32, 4).into());
+        s.switch(state.into()).is(Const::new(3u32, 2), |s| { s.assign(&out, Const::new(15u32, 4).into()); });
+    });
+    m.borrow_mut().comb(|s| {
+        s.switch(req.into()).casez().priority()
+            .pattern("???1", |s| { s.assign(&grant, Const::new(0u32, 2).into()); })
+            .pattern("??1?", |s| { s.assign(&grant, Const::new(1u32, 2).into()); })
+            .pattern("?1??", |s| { s.assign(&grant, Const::new(2u32, 2).into()); })
+            .default(|s| { s.assign(&grant, Const::new(3u32, 2).into()); });
+    });
+
+    let sv = m.synth();
+    assert!(sv.contains("always_ff @(posedge clk) begin\nunique case (state)\n0: begin\nif (go) begin\nstate <= 2'h1;\nend\nend\n1, 2: begin\nstate <= 2'(state + 1);\nend\ndefault: begin\nstate <= 2'h0;\nend\nendcase\nend\n"));
+    assert!(sv.contains("always_comb begin\nout = 4'h0;\ncase (state)\n2'h3: begin\nout = 4'hf;\nend\nendcase\nend\n"));
+    assert!(sv.contains("always_comb begin\npriority casez (req)\n4'b???1: begin\ngrant = 2'h0;\nend\n4'b??1?: begin\ngrant = 2'h1;\nend\n4'b?1??: begin\ngrant = 2'h2;\nend\ndefault: begin\ngrant = 2'h3;\nend\nendcase\nend\n"));
+    assert!(m.borrow().warnings().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "wildcard patterns need casez or casex")]
+fn case_pattern_without_casez() {
+    let mut s = Scope::new();
+    let a = Signal::new("a", 2);
+    s.switch(a.into()).pattern("1?", |_| {});
+}
 
-    assert_eq!(m.synth(), "module sync();\n\nalways_ff @(posedge clk) begin\nc <= (a + 1);\nif ((a == 1)) begin\nb <= (a + c);\nend\nelse begin\nb <= ((a + c) + 2);\nend\nend\n\nendmodule\n");
+#[test]
+#[should_panic(expected = "width mismatch: case on 'a' is 2 bits wide but '4' is 3 bits")]
+fn case_label_too_wide() {
+    let mut s = Scope::new();
+    let a = Signal::new("a", 2);
+    s.switch(a.into()).is(4u32, |_| {});
 }
 
 #[test]
 fn signal_conds() {
     let mut m = Module::new("cond");
-    let comp = Signal::new("comp", 32);
-    let a = Signal::new("a", 32);
-    let b = Signal::new("b", 32);
-    let c = Signal::new("c", 32);
-    let d = Signal::new("d", 32);
-    let e = Signal::new("e", 32);
-    let f = Signal::new("f", 32);
-    let g = Signal::new("g", 32);
+    let comp = m.logic("comp", 32);
+    let a = m.logic("a", 1);
+    let b = m.logic("b", 1);
+    let c = m.logic("c", 1);
+    let d = m.logic("d", 1);
+    let e = m.logic("e", 1);
+    let f = m.logic("f", 1);
+    let g = m.logic("g", 1);
+
+    m += comp;
+    m -= g;
 
-    m[a] = true.into();
-    m[b] = (a == 1).into();
-    m[c] = (b != comp).into();
-    m[d] = (c >= 1).into();
-    m[e] = (d <= comp).into();
-    m[f] = (e > 1).into();
-    m[g] = (f < 1).into();
+    comb!(a := Const::new(1u32, 1)).unwrap();
+    comb!(b := a.eq(1)).unwrap();
+    comb!(c := b.ne(comp)).unwrap();
+    comb!(d := c.ge(1)).unwrap();
+    comb!(e := d.le(comp)).unwrap();
+    comb!(f := e.gt(1)).unwrap();
+    comb!(g := f.lt(1)).unwrap();
 
-    assert_eq!(m.synth(), "module cond();\nassign g = ((f < 1));\nassign a = (1);\nassign b = ((a == 1));\nassign c = ((b != comp));\nassign d = ((c >= 1));\nassign e = ((d <= comp));\nassign f = ((e > 1));\nendmodule\n");
+    assert_eq!(m.synth(), "module cond(input logic [31:0] comp, output logic [0:0] g);\nlogic [0:0] a;\nlogic [0:0] b;\nlogic [0:0] c;\nlogic [0:0] d;\nlogic [0:0] e;\nlogic [0:0] f;\nassign a = 1'h1;\nassign b = (a == 1);\nassign c = (b != comp);\nassign d = (c >= 1);\nassign e = (d <= comp);\nassign f = (e > 1);\nassign g = (f < 1);\nendmodule\n");
 }
-*/
 
 #[test]
 fn complex_conds() {
@@ -131,6 +1230,308 @@ fn complex_conds() {
     let a = m.logic("a", 32);
     let b = m.logic("b", 32);
 
-    comb!(a := b + 1);
-    comb!(a := b + 1);
+    assert!(comb!(a := b + 1).is_ok());
+    assert_eq!(comb!(a := b + 1).unwrap_err().to_string(), "'a' is already driven in module 'sync'");
+    assert_eq!(m.synth(), "module sync();\nlogic [31:0] a;\nlogic [31:0] b;\nassign a = (b + 1);\nendmodule\n");
+
+    let mut m = Module::new("ports");
+    let x = m.logic("x", 4);
+    let y = m.logic("y", 4);
+    m += x;
+    assert!(comb!(x := y).is_err());
+    m.borrow_mut().comb(|s| { s.assign(&y, x.into()); });
+    assert!(comb!(y := x).is_err());
+
+    let free = Signal::new("free", 4);
+    assert!(comb!(free := x).is_err());
+
+    drop(m);
+    assert!(comb!(y := x).is_err());
+}
+
+#[test]
+fn drivers() {
+    let child = adder(4);
+    let m = Module::new("drivers");
+    let clk = m.logic("clk", 1);
+    let a = m.logic("a", 4);
+    let b = m.logic("b", 4);
+    let w = m.logic("w", 4);
+    let x = m.logic("x", 4);
+    let data = m.logic("data", 4);
+    let addr = m.logic("addr", 2);
+    let mem = m.memory("mem", 4, 4);
+    m.borrow_mut().try_add_input(b).unwrap();
+    let connect = |o: Signal| vec![("a", expr::Op::from(b)), ("b", b.into()), ("o", o.into())];
+    let driven = |signal: &str| KungError::DuplicateDriver { signal: String::from(signal), module: String::from("drivers") };
+
+    // a continuous assign, then a block
+    comb!(a := b).unwrap();
+    assert_eq!(m.borrow_mut().try_comb(|s| { s.assign(&a, b.into()); }), Err(vec![driven("a")]));
+    assert_eq!(m.borrow_mut().try_on(&clk, |s| { s.assign(&a, b.into()); }), Err(vec![driven("a")]));
+    assert_eq!(m.borrow_mut().try_instance(&child, "u_a", connect(a)), Err(driven("a")));
+
+    // a block, then a block or a continuous assign
+    m.borrow_mut().on(&clk, |s| { s.assign(&x, b.into()); });
+    assert_eq!(m.borrow_mut().try_on(&clk, |s| { s.assign(&x, b.into()); }), Err(vec![driven("x")]));
+    assert_eq!(m.borrow_mut().try_instance(&child, "u_x", connect(x)), Err(driven("x")));
+    assert_eq!(comb!(x := b), Err(driven("x")));
+
+    // an instance, then another one or a block
+    m.borrow_mut().instance(&child, "u_w", connect(w));
+    assert_eq!(m.borrow_mut().try_instance(&child, "u_w2", connect(w)), Err(driven("w")));
+    assert_eq!(m.borrow_mut().try_comb(|s| { s.assign(&w, b.into()); }), Err(vec![driven("w")]));
+    assert_eq!(comb!(w := b), Err(driven("w")));
+
+    // a ROM, then a continuous assign
+    m.borrow_mut().rom(&data, &addr, vec![1u32, 2]);
+    assert_eq!(comb!(data := b), Err(driven("data")));
+
+    // memories loaded by initial can still be written by a block
+    m.borrow_mut().init(&mem, vec![1u32]);
+    m.borrow_mut().on(&clk, |s| { s.write(&mem, addr.into(), b.into()); });
+    let word = expr::Assign::indexed(&mem, Const::new(0u32, 2).into(), b.into());
+    assert_eq!(m.borrow_mut().try_assign(word), Err(driven("mem[2'h0]")));
+
+    assert!(matches!(m.borrow_mut().try_comb(|s| { s.assign(&b, a.into()); }).unwrap_err()[..],
+        [KungError::InvalidDirection { instance: None, .. }]));
+    assert_eq!(m.synth().matches("always_ff").count(), 2);
+}
+
+#[test]
+#[should_panic(expected = "'data' is already driven in module 'rom'")]
+fn rom_driven_twice() {
+    let m = Module::new("rom");
+    let data = m.logic("data", 4);
+    let addr = m.logic("addr", 2);
+    comb!(data := Const::new(0u32, 4)).unwrap();
+    m.borrow_mut().rom(&data, &addr, vec![1u32, 2]);
+}
+
+#[test]
+#[should_panic(expected = "'mem' is already driven in module 'init'")]
+fn memory_initialized_twice() {
+    let m = Module::new("init");
+    let mem = m.memory("mem", 4, 4);
+    m.borrow_mut().init(&mem, vec![1u32]);
+    m.borrow_mut().readmem(&mem, "mem.hex", Radix::Hex, vec![2u32]);
+}
+
+#[test]
+fn seq_registers() {
+    use crate::hdl::condition::Reset;
+
+    let mut m = Module::new("regs");
+    let clk = m.logic("clk", 1);
+    let fast = m.logic("fast", 1);
+    let rst_n = m.logic("rst_n", 1);
+    let d = m.logic("d", 8);
+    let a = m.logic("a", 8);
+    let b = m.logic("b", 8);
+    let c = m.reg("c", 8, 7u32);
+    let e = m.logic("e", 8);
+
+    m += clk;
+    m += fast;
+    m += rst_n;
+    m += d;
+    m -= a;
+    m -= b;
+    m -= c;
+    m -= e;
+
+    seq!(clk, a <= d).unwrap();
+    seq!(clk, Reset::AsyncLow(rst_n), c <= d).unwrap();
+    seq!(clk, b <= a).unwrap();
+    seq!(fast, e <= d ^ a).unwrap();
+    assert_eq!(seq!(fast, b <= d).unwrap_err().to_string(), "'b' is already driven in module 'regs'");
+    assert!(comb!(a := d).is_err());
+    assert!(seq!(clk, d <= a).is_err());
+
+    assert_eq!(m.synth(), "module regs(input logic [0:0] clk, input logic [7:0] d, input logic [0:0] fast, input logic [0:0] rst_n, output logic [7:0] a, output logic [7:0] b, output logic [7:0] c, output logic [7:0] e);\n\nalways_ff @(posedge clk) begin\na <= d;\nb <= a;\nend\n\n\nalways_ff @(posedge clk or negedge rst_n) begin\nif (!rst_n) begin\nc <= 8'h7;\nend\nelse begin\nc <= d;\nend\nend\n\n\nalways_ff @(posedge fast) begin\ne <= (d ^ a);\nend\n\nendmodule\n");
+    assert!(m.borrow().warnings().is_empty());
+}
+
+#[test]
+fn hdl_macro() {
+    let m = Module::new("alu");
+    let op = m.logic("op", 2);
+    let en = m.logic("en", 1);
+    let a = m.logic("a", 8);
+    let b = m.logic("b", 8);
+    let o = m.logic("o", 8);
+    let f = m.logic("f", 2);
+
+    let zero = Const::new(0u32, 8);
+    m.borrow_mut().comb(|s| hdl!(s =>
+        o = zero.clone();
+        if op == 0 && en != 0 {
+            o = a + b;
+        } else if op == 1 {
+            o = a - b;
+        } else {
+            o = a & b;
+        }
+        match op {
+            0 => f = Const::new(1u32, 2),
+            1 | 2 => {
+                f = Const::new(2u32, 2);
+            }
+            _ => f = Const::new(3u32, 2),
+        }
+    ));
+
+    assert_eq!(m.synth(), "module alu();\nlogic [7:0] a;\nlogic [7:0] b;\nlogic [0:0] en;\nlogic [1:0] f;\nlogic [7:0] o;\nlogic [1:0] op;\n\nalways_comb begin\no = 8'h0;\nif (((op == 0) && (en != 0))) begin\no = (a + b);\nend\nelse if ((op == 1)) begin\no = (a - b);\nend\nelse begin\no = (a & b);\nend\ncase (op)\n0: begin\nf = 2'h1;\nend\n1, 2: begin\nf = 2'h2;\nend\ndefault: begin\nf = 2'h3;\nend\nendcase\nend\n\nendmodule\n");
+}
+
+#[test]
+fn hdl_macro_literals() {
+    let m = Module::new("lit");
+    let a = m.logic("a", 4);
+    let b = m.logic("b", 4);
+    let c = m.logic("c", 4);
+    let clk = m.logic("clk", 1);
+    let mem = m.memory("mem", 8, 4);
+
+    m.borrow_mut().comb(|s| hdl!(s =>
+        if a == 1 { b = a + c; } else { b = 0; }
+    ));
+    m.borrow_mut().on(&clk, |s| hdl!(s =>
+        mem[a.slice(1, 0)] = 255;
+    ));
+
+    let synth = m.synth();
+    assert!(synth.contains("always_comb begin\nif ((a == 1)) begin\nb = (a + c);\nend\nelse begin\nb = 4'h0;\nend\nend\n"));
+    assert!(synth.contains("mem[a[1:0]] <= 8'hff;\n"));
+}
+
+#[test]
+fn hdl_macro_sync() {
+    let m = Module::new("prio");
+    let clk = m.logic("clk", 1);
+    let req = m.logic("req", 3);
+    let grant = m.logic("grant", 2);
+    let mem = m.memory("mem", 2, 4);
+
+    m.borrow_mut().on(&clk, |s| hdl!(s =>
+        match req {
+            "??1" => grant = Const::new(0u32, 2),
+            "?1?" => grant = Const::new(1u32, 2),
+            _ => {
+                grant = Const::new(3u32, 2);
+                if req == 0 {
+                    mem[grant] = grant;
+                }
+            }
+        }
+    ));
+
+    assert_eq!(m.synth(), "module prio();\nlogic [0:0] clk;\nlogic [1:0] grant;\nlogic [1:0] mem [0:3];\nlogic [2:0] req;\n\nalways_ff @(posedge clk) begin\ncasez (req)\n3'b??1: begin\ngrant <= 2'h0;\nend\n3'b?1?: begin\ngrant <= 2'h1;\nend\ndefault: begin\ngrant <= 2'h3;\nif ((req == 0)) begin\nmem[grant] <= grant;\nend\nend\nendcase\nend\n\nendmodule\n");
+}
+
+#[test]
+fn try_builders() {
+    use crate::hdl::condition::Edge;
+
+    let m = Module::new("mems");
+    let clk = m.logic("clk", 1);
+    let addr = m.logic("addr", 2);
+    let data = m.logic("data", 4);
+    let q = m.logic("q", 4);
+    let mem = m.memory("mem", 4, 2);
+    let driven = |signal: &str| KungError::DuplicateDriver { signal: String::from(signal), module: String::from("mems") };
+
+    assert_eq!(m.borrow_mut().try_init(&data, vec![1u32]), Err(KungError::NotMemory(String::from("data"))));
+    assert_eq!(m.borrow_mut().try_readmem(&mem, "mem.txt", Radix::Dec, vec![1u32]), Err(KungError::MemFileRadix(Radix::Dec)));
+    assert_eq!(m.borrow_mut().try_init(&mem, vec![1u32, 2, 3]).unwrap_err().to_string(), "3 words don't fit in 'mem' of 2 words");
+    assert_eq!(m.borrow_mut().try_rom(&data, &addr, vec![16u32]).unwrap_err().to_string(), "word 0 of 'data' (16) doesn't fit in 4 bits");
+    assert!(m.borrow().mem_files().is_empty());
+
+    m.borrow_mut().try_rom(&data, &addr, vec![1u32]).unwrap();
+    assert_eq!(m.borrow_mut().try_read_port(&data, &mem, addr.into()), Err(driven("data")));
+    assert_eq!(m.borrow_mut().try_read_port_on(&clk, &data, &mem, addr.into()), Err(vec![driven("data")]));
+    assert!(matches!(m.borrow_mut().try_read_port(&addr, &mem, addr.into()), Err(KungError::WidthMismatch { .. })));
+    m.borrow_mut().try_read_port_on(&clk, &q, &mem, addr.into()).unwrap();
+
+    assert_eq!(m.borrow_mut().try_on_negedge(&clk, |s| { s.assign(&q, data.into()); }), Err(vec![driven("q")]));
+    assert_eq!(m.borrow_mut().try_latch(|s| { s.assign(&q, data.into()); }), Err(vec![driven("q")]));
+    assert_eq!(m.borrow_mut().try_on_events(&[], |_| {}), Err(vec![KungError::NoEvents(String::from("mems"))]));
+    assert_eq!(m.borrow_mut().try_on_events(&[(Edge::Pos, clk)], |s| { s.assign(&q, data.into()); }), Err(vec![driven("q")]));
+
+    let s: Stream = m.bundle("s");
+    m.borrow_mut().try_add_output(s.data).unwrap();
+    assert!(matches!(m.borrow_mut().try_port_bundle(&s), Err(KungError::Duplicate { kind: "output", .. })));
+    assert_eq!(m.borrow().ports().len(), 1);
+
+    let top = Module::new("top");
+    let x = top.logic("x", 8);
+    let y = top.logic("y", 4);
+    top.borrow_mut().instance(&adder(8), "u_first", [("a", x.into()), ("b", x.into()), ("o", x.into())]);
+    top.borrow_mut().instance(&adder(4), "u_second", [("a", y.into()), ("b", y.into()), ("o", y.into())]);
+    assert_eq!(Design::new(&top).try_modules().err(), Some(KungError::DuplicateModule(String::from("adder"))));
+}
+
+#[test]
+fn errors() {
+    let m = Module::new("gen");
+    let a = m.try_logic("a", 8).unwrap();
+    let b = m.logic("b", 4);
+
+    let long = "x".repeat(65);
+    assert_eq!(m.try_logic(&long, 1).err(), Some(KungError::NameTooLong(long.clone())));
+    assert_eq!(m.try_logic("z", 0).err().unwrap().to_string(), "signal 'z' can't be 0 bits wide");
+    assert_eq!(m.try_logic("a", 8).err().unwrap().to_string(), "signal with name 'a' already defined in module 'gen'");
+    assert!(matches!(m.try_reg("r", 2, 7u32), Err(KungError::Overflow { width: 2, .. })));
+    assert!(matches!(m.borrow_mut().try_param(&long, 1), Err(KungError::NameTooLong(_))));
+
+    m.borrow_mut().try_add_input(b).unwrap();
+    assert!(matches!(m.borrow_mut().try_add_input(b), Err(KungError::Duplicate { kind: "input", .. })));
+    assert_eq!(m.borrow_mut().try_add_output(b).err().unwrap().to_string(), "input with name 'b' already defined in module 'gen'");
+    assert!(matches!(comb!(b := Const::new(0u32, 4)), Err(KungError::InvalidDirection { instance: None, .. })));
+
+    assert_eq!(expr::Assign::try_new(&a, b.into()).err(), Some(KungError::WidthMismatch {
+        dest: String::from("'a'"),
+        dest_width: 8,
+        expr: String::from("b"),
+        width: 4,
+    }));
+    assert!(matches!(comb!(a := b), Err(KungError::WidthMismatch { .. })));
+    m.borrow_mut().try_assign(expr::Assign::new(&a, b.zext(8))).unwrap();
+    assert!(matches!(comb!(a := b.zext(8)), Err(KungError::DuplicateDriver { .. })));
+
+    let clk = m.logic("clk", 1);
+    let q = m.logic("q", 8);
+    m.borrow_mut().on(&clk, |s| { s.assign(&q, a.into()); });
+    assert!(matches!(m.borrow_mut().try_assign(expr::Assign::new(&q, a.into())), Err(KungError::DuplicateDriver { .. })));
+    assert!(matches!(m.borrow_mut().try_assign(expr::Assign::new(&b, b.into())), Err(KungError::InvalidDirection { .. })));
+
+    let words = m.array("words", 4, &[2]);
+    m.borrow_mut().try_assign(expr::Assign::indexed(&words, Const::new(0u32, 1).into(), b.into())).unwrap();
+    m.borrow_mut().try_assign(expr::Assign::indexed(&words, Const::new(1u32, 1).into(), b.into())).unwrap();
+    assert!(matches!(m.borrow_mut().try_assign(expr::Assign::new(&words, a.into())), Err(KungError::DuplicateDriver { .. })));
+
+    let w = m.borrow_mut().param("W", 8);
+    assert!(matches!(m.try_logic_param("a", w), Err(KungError::Duplicate { kind: "signal", .. })));
+    assert!(matches!(m.try_memory(&long, 8, 4), Err(KungError::NameTooLong(_))));
+    assert!(matches!(m.try_array("a", 8, &[2]), Err(KungError::Duplicate { .. })));
+    let _: Stream = m.try_bundle("s").unwrap();
+    assert!(matches!(m.try_bundle::<Stream>("s"), Err(KungError::Duplicate { .. })));
+    let _: Stream = m.try_interface("bus").unwrap();
+    assert_eq!(m.try_interface::<Stream>("bus").err().unwrap().to_string(), "interface with name 'bus' already defined in module 'gen'");
+    assert!(matches!(m.try_interface::<Stream>(&"y".repeat(60)), Err(KungError::NameTooLong(_))));
+
+    let child = adder(8);
+    let connect = |connections: Vec<(&str, expr::Op)>| {
+        m.borrow_mut().try_instance(&child, "u_add", connections).unwrap_err().to_string()
+    };
+    assert_eq!(connect(vec![("a", a.into()), ("b", a.into())]), "port 'o' of 'adder' is not connected in instance 'u_add'");
+    assert_eq!(connect(vec![("a", a.into()), ("b", a.into()), ("o", a + 1u32)]),
+        "port 'o' of 'adder' is an output, it can only be connected to a signal, not '(a + 1)'");
+    assert_eq!(connect(vec![("a", a.into()), ("b", a.into()), ("o", b.into())]),
+        "width mismatch: port 'o' of 'adder' is 8 bits wide but 'b' is 4 bits, use resize() to convert");
+    assert_eq!(connect(vec![("a", a.into()), ("b", a.into()), ("o", a.into()), ("c", a.into())]),
+        "'adder' has no port named 'c'");
+    assert!(matches!(m.borrow_mut().try_instance_with_params(&child, "u_add", &[("W", 8)], Vec::<(&str, expr::Op)>::new()),
+        Err(KungError::UnknownParam { .. })));
+    assert!(m.borrow().instances().is_empty());
 }